@@ -0,0 +1,113 @@
+//! How the cost of finding overlapping colliders grows with their number, checking every pair
+//! against going through `UniformGrid`. Run with `cargo bench --bench collisions`.
+
+use bevy::math::Vec2;
+use jump_or_die::broadphase::{self, UniformGrid};
+use jump_or_die::world;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+use std::time::{Duration, Instant};
+
+const COUNTS: [usize; 5] = [10, 100, 1_000, 5_000, 10_000];
+const ROUNDS: u32 = 10;
+
+struct Box2 {
+    min: Vec2,
+    max: Vec2,
+}
+
+fn main() {
+    println!(
+        "{:>8} {:>14} {:>14} {:>14} {:>12}",
+        "colliders", "all pairs", "grid pairs", "grid player", "overlaps"
+    );
+
+    let mut grid = UniformGrid::default();
+    for &count in COUNTS.iter() {
+        let boxes = random_boxes(count);
+
+        let (brute_force_time, brute_force_overlaps) =
+            measure(|| brute_force_overlaps(&boxes));
+        let (grid_time, grid_overlaps) = measure(|| grid_overlaps(&boxes, &mut grid));
+        assert_eq!(brute_force_overlaps, grid_overlaps);
+
+        let (player_time, _) = measure(|| player_overlaps(&boxes, &mut grid));
+
+        println!(
+            "{:>8} {:>14?} {:>14?} {:>14?} {:>12}",
+            count, brute_force_time, grid_time, player_time, brute_force_overlaps
+        );
+    }
+}
+
+/// Pickup and projectile sized boxes spread over the screen.
+fn random_boxes(count: usize) -> Vec<Box2> {
+    let mut rng = StdRng::seed_from_u64(count as u64);
+    let half_screen = Vec2::new(world::SCREEN_WIDTH, world::SCREEN_HEIGHT) / 2.0;
+    (0..count)
+        .map(|_| {
+            let center = Vec2::new(
+                rng.gen_range(-half_screen.x, half_screen.x),
+                rng.gen_range(-half_screen.y, half_screen.y),
+            );
+            let half_size = Vec2::splat(rng.gen_range(4.0, 24.0));
+            Box2 {
+                min: center - half_size,
+                max: center + half_size,
+            }
+        })
+        .collect()
+}
+
+fn brute_force_overlaps(boxes: &[Box2]) -> usize {
+    let mut overlaps = 0;
+    for (i, a) in boxes.iter().enumerate() {
+        for b in &boxes[i + 1..] {
+            if broadphase::overlaps(a.min, a.max, b.min, b.max) {
+                overlaps += 1;
+            }
+        }
+    }
+    overlaps
+}
+
+/// What `physics::detect_collisions` does each step: every collider against every other one.
+fn grid_overlaps(boxes: &[Box2], grid: &mut UniformGrid<usize>) -> usize {
+    fill(boxes, grid);
+
+    let mut overlaps = 0;
+    let mut found = Vec::new();
+    for (i, a) in boxes.iter().enumerate() {
+        found.clear();
+        grid.query(a.min, a.max, &mut found);
+        overlaps += found.iter().filter(|&&j| j > i).count();
+    }
+    overlaps
+}
+
+/// One player against everything, the old per-player lookup.
+fn player_overlaps(boxes: &[Box2], grid: &mut UniformGrid<usize>) -> usize {
+    fill(boxes, grid);
+
+    let mut found = Vec::new();
+    grid.query(Vec2::new(-25.0, 0.0), Vec2::new(25.0, 50.0), &mut found);
+    found.len()
+}
+
+fn fill(boxes: &[Box2], grid: &mut UniformGrid<usize>) {
+    grid.clear();
+    for (i, b) in boxes.iter().enumerate() {
+        grid.insert(b.min, b.max, i);
+    }
+}
+
+/// Average time of a round and the result of the last one.
+fn measure<F: FnMut() -> usize>(mut f: F) -> (Duration, usize) {
+    let mut result = 0;
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        result = f();
+    }
+    (start.elapsed() / ROUNDS, result)
+}
@@ -1,12 +1,28 @@
+use crate::game;
+use crate::player::Ability;
 use bevy::prelude::*;
 use rand::Rng;
 
 use std::time::Duration;
 
+pub const MIN_HEALTH_INTERVAL: f32 = 5.0;
+pub const MAX_HEALTH_INTERVAL: f32 = 15.0;
+
+pub const MIN_ABILITY_INTERVAL: f32 = 20.0;
+pub const MAX_ABILITY_INTERVAL: f32 = 40.0;
+pub const ABILITY_PICKUP_SIZE: f32 = 32.0;
+/// Pickups are round, with this radius relative to their sprite's width.
+pub const PICKUP_RADIUS: f32 = 0.6;
+
 #[derive(Copy, Clone)]
 pub enum Award {
     Score(f32),
     Health(u8),
+    Ability(Ability),
+}
+
+pub struct AwardMaterials {
+    pub health: Handle<ColorMaterial>,
 }
 
 pub struct AwardTimer {
@@ -17,19 +33,31 @@ pub struct AwardTimer {
 
 impl AwardTimer {
     pub fn new(min_time: f32, max_time: f32) -> Self {
-        let mut timer = Self {
-            timer: Timer::new(Duration::from_secs_f32(0.0), true),
+        Self {
+            timer: Timer::new(Duration::from_secs_f32(max_time), true),
             min_time,
             max_time,
-        };
-        timer.refill();
-        timer
+        }
     }
 
-    pub fn refill(&mut self) {
-        let mut rng = rand::thread_rng();
+    /// Changes the refill range, keeping the current countdown within it.
+    pub fn set_range(&mut self, min_time: f32, max_time: f32) {
+        self.min_time = min_time;
+        self.max_time = max_time;
+        game::clamp_interval(&mut self.timer, min_time, max_time);
+    }
+
+    pub fn refill<R: Rng>(&mut self, rng: &mut R) {
         self.timer.set_duration(Duration::from_secs_f32(
             rng.gen_range(self.min_time, self.max_time),
         ));
     }
+
+    pub fn restart<R: Rng>(&mut self, rng: &mut R) {
+        self.refill(rng);
+        self.timer.reset();
+    }
 }
+
+/// Spawns ability pickups, apart from the health ones.
+pub struct AbilityTimer(pub AwardTimer);
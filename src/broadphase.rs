@@ -0,0 +1,179 @@
+use bevy::math::Vec2;
+use std::collections::HashMap;
+
+pub const CELL_SIZE: f32 = 64.0;
+
+/// Uniform grid of axis-aligned boxes. Finds the boxes that may overlap a given one without
+/// checking every pair.
+pub struct UniformGrid<T> {
+    cell_size: f32,
+    cells: HashMap<(i32, i32), Vec<usize>>,
+    items: Vec<(Vec2, Vec2, T)>,
+    scratch: Vec<usize>,
+    /// Last query each item was visited by, so items in several cells are checked once.
+    visits: Vec<u32>,
+    query_id: u32,
+}
+
+impl<T: Copy> UniformGrid<T> {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            items: Vec::new(),
+            scratch: Vec::new(),
+            visits: Vec::new(),
+            query_id: 0,
+        }
+    }
+
+    /// Removes every box, keeping the memory for the next round.
+    pub fn clear(&mut self) {
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        self.items.clear();
+        self.visits.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn insert(&mut self, min: Vec2, max: Vec2, item: T) {
+        let index = self.items.len();
+        self.items.push((min, max, item));
+        self.visits.push(self.query_id);
+
+        let ((min_x, min_y), (max_x, max_y)) = (self.cell(min), self.cell(max));
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                self.cells.entry((x, y)).or_insert_with(Vec::new).push(index);
+            }
+        }
+    }
+
+    /// Appends the items whose boxes overlap `min..max` to `found`, in insertion order.
+    pub fn query(&mut self, min: Vec2, max: Vec2, found: &mut Vec<T>) {
+        self.scratch.clear();
+        self.query_id = self.query_id.wrapping_add(1);
+        if self.query_id == 0 {
+            self.visits.iter_mut().for_each(|visit| *visit = 0);
+            self.query_id = 1;
+        }
+
+        let ((min_x, min_y), (max_x, max_y)) = (self.cell(min), self.cell(max));
+        for x in min_x..=max_x {
+            for y in min_y..=max_y {
+                let cell = match self.cells.get(&(x, y)) {
+                    Some(cell) => cell,
+                    None => continue,
+                };
+                for &index in cell {
+                    if self.visits[index] == self.query_id {
+                        continue;
+                    }
+                    self.visits[index] = self.query_id;
+
+                    let (item_min, item_max, _) = self.items[index];
+                    if overlaps(min, max, item_min, item_max) {
+                        self.scratch.push(index);
+                    }
+                }
+            }
+        }
+
+        self.scratch.sort_unstable();
+        let items = &self.items;
+        found.extend(self.scratch.iter().map(|&index| items[index].2));
+    }
+
+    fn cell(&self, point: Vec2) -> (i32, i32) {
+        (
+            (point.x / self.cell_size).floor() as i32,
+            (point.y / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+impl<T: Copy> Default for UniformGrid<T> {
+    fn default() -> Self {
+        Self::new(CELL_SIZE)
+    }
+}
+
+pub fn overlaps(a_min: Vec2, a_max: Vec2, b_min: Vec2, b_max: Vec2) -> bool {
+    a_min.x <= b_max.x && b_min.x <= a_max.x && a_min.y <= b_max.y && b_min.y <= a_max.y
+}
+
+/// Box covering a box of `size` moving from `start` to `end`.
+pub fn swept_bounds(start: Vec2, end: Vec2, size: Vec2) -> (Vec2, Vec2) {
+    let half_size = size / 2.0;
+    (start.min(end) - half_size, start.max(end) + half_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_boxes(rng: &mut StdRng, count: usize) -> Vec<(Vec2, Vec2)> {
+        (0..count)
+            .map(|_| {
+                let min = Vec2::new(rng.gen_range(-500.0, 500.0), rng.gen_range(-500.0, 500.0));
+                let size = Vec2::new(rng.gen_range(1.0, 200.0), rng.gen_range(1.0, 200.0));
+                (min, min + size)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn query_finds_the_same_boxes_as_checking_every_one() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let boxes = random_boxes(&mut rng, 300);
+        let mut grid = UniformGrid::default();
+        for (index, &(min, max)) in boxes.iter().enumerate() {
+            grid.insert(min, max, index);
+        }
+        assert_eq!(grid.len(), boxes.len());
+
+        let mut found = Vec::new();
+        for (min, max) in random_boxes(&mut rng, 300) {
+            found.clear();
+            grid.query(min, max, &mut found);
+
+            let expected: Vec<usize> = (0..boxes.len())
+                .filter(|&index| overlaps(min, max, boxes[index].0, boxes[index].1))
+                .collect();
+            assert_eq!(found, expected);
+        }
+    }
+
+    #[test]
+    fn boxes_in_several_cells_are_found_once() {
+        let mut grid = UniformGrid::new(10.0);
+        grid.insert(Vec2::new(-25.0, -25.0), Vec2::new(25.0, 25.0), 1);
+        grid.insert(Vec2::new(5.0, 5.0), Vec2::new(6.0, 6.0), 2);
+
+        let mut found = Vec::new();
+        grid.query(Vec2::new(-30.0, -30.0), Vec2::new(30.0, 30.0), &mut found);
+        assert_eq!(found, vec![1, 2]);
+    }
+
+    #[test]
+    fn clear_removes_every_box() {
+        let mut grid = UniformGrid::new(10.0);
+        grid.insert(Vec2::ZERO, Vec2::new(5.0, 5.0), 1);
+        grid.clear();
+        assert!(grid.is_empty());
+
+        let mut found = Vec::new();
+        grid.query(Vec2::ZERO, Vec2::new(5.0, 5.0), &mut found);
+        assert!(found.is_empty());
+    }
+}
@@ -17,6 +17,16 @@ pub enum EffectDuration {
 pub trait EntityEffect: Send + Sync {
     fn apply(&mut self, entity: Entity, velocity: &mut Velocity, transform: &mut Transform);
     fn undo(&mut self, entity: Entity, velocity: &mut Velocity, transform: &mut Transform);
+
+    /// Whether player input is ignored while the effect lasts.
+    fn stuns(&self) -> bool {
+        false
+    }
+
+    /// Whether the effect multiplies the velocity.
+    fn boosts(&self) -> bool {
+        false
+    }
 }
 
 pub struct EntityEffectDescriptor {
@@ -59,6 +69,10 @@ impl EntityEffectDescriptor {
             EffectDuration::Temporary(duration) => duration == Duration::from_secs(0),
         }
     }
+
+    pub fn stuns(&self) -> bool {
+        !self.is_expired() && self.effect.stuns()
+    }
 }
 
 #[derive(Default)]
@@ -66,6 +80,17 @@ pub struct EntityEffects {
     pub active: Vec<EntityEffectDescriptor>,
 }
 
+impl EntityEffects {
+    pub fn is_stunned(&self) -> bool {
+        self.active.iter().any(|effect| effect.stuns())
+    }
+
+    /// Removes every speed boost, so none of them is applied on top of a new velocity.
+    pub fn drop_boosts(&mut self) {
+        self.active.retain(|effect| !effect.effect.boosts());
+    }
+}
+
 pub struct SpeedBoost {
     pub boost: Vec2,
 }
@@ -97,6 +122,22 @@ impl EntityEffect for SpeedBoost {
     fn undo(&mut self, entity: Entity, velocity: &mut Velocity, transform: &mut Transform) {
         velocity.drop_boost();
     }
+
+    fn boosts(&self) -> bool {
+        true
+    }
+}
+
+/// Leaves the velocity to the knockback that came with it and ignores player input.
+pub struct HitStun;
+
+impl EntityEffect for HitStun {
+    fn apply(&mut self, _entity: Entity, _velocity: &mut Velocity, _transform: &mut Transform) {}
+    fn undo(&mut self, _entity: Entity, _velocity: &mut Velocity, _transform: &mut Transform) {}
+
+    fn stuns(&self) -> bool {
+        true
+    }
 }
 
 pub struct ActiveEffects {
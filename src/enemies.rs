@@ -3,6 +3,9 @@ use bevy::core::Timer;
 
 pub const ENEMY_WIDTH: f32 = 50.0;
 pub const ENEMY_HEIGHT: f32 = 50.0;
+/// What hurts the player: a bit smaller than the sprite and standing on its bottom.
+pub const HITBOX_WIDTH: f32 = 40.0;
+pub const HITBOX_HEIGHT: f32 = 40.0;
 
 pub const VELOCITY_X: f32 = 200.0;
 pub const VELOCITY_Y: f32 = 0.0;
@@ -11,9 +14,31 @@ pub const INITIAL_POSITION_X: f32 = (world::SCREEN_WIDTH + ENEMY_WIDTH) / 2.0;
 pub const INITIAL_POSITION_Y: f32 = ENEMY_HEIGHT / 2.0;
 
 pub const SCORE: f32 = 50.0;
+pub const STOMP_SCORE: f32 = 100.0;
+
+pub const KNOCKBACK_SPEED: f32 = 500.0;
+pub const KNOCKBACK_LIFT: f32 = 300.0;
+pub const HIT_STUN_TIME: f32 = 0.3;
+
+pub const FLYING_WIDTH: f32 = 60.0;
+pub const FLYING_HEIGHT: f32 = 24.0;
+/// Chance that an obstacle is a flying enemy instead of a walking one.
+pub const FLYING_CHANCE: f32 = 0.2;
+/// Gap between the ground and a flying enemy, only a crouching player fits under it.
+pub const FLYING_ALTITUDE: f32 = 34.0;
+pub const FLYING_KNOCKBACK_SPEED: f32 = 400.0;
+pub const FLYING_KNOCKBACK_LIFT: f32 = 0.0;
+pub const FLYING_HIT_STUN_TIME: f32 = 0.2;
+
+pub const INITIAL_SPAWN_DELAY: f32 = 3.0;
+pub const MIN_SPAWN_INTERVAL: f32 = 2.0;
+pub const MAX_SPAWN_INTERVAL: f32 = 3.0;
 
 pub struct Enemy;
 
+/// Enemy that keeps its height instead of walking on the ground.
+pub struct Flying;
+
 pub struct SpawnTimer {
     pub timer: Timer,
 }
@@ -1,11 +1,17 @@
 use bevy::prelude::*;
+use std::time::Duration;
 
 pub struct GameEntity;
 
+/// Stages of a single simulation step, in the order they run.
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
 pub enum GameStage {
     AcceptInput,
-    Game,
+    Effects,
+    Movement,
+    Collisions,
+    Events,
+    Cleanup,
 }
 
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
@@ -27,4 +33,97 @@ impl PartialEq<GameState> for State<GameState> {
 pub struct Game {
     pub score: f32,
     pub best_score: f32,
+    pub time_survived: f32,
+}
+
+pub const SIMULATION_STEP: f64 = 1.0 / 60.0;
+
+const MAX_STEPS_PER_FRAME: u32 = 5;
+
+/// Fixed-step schedule running the gameplay systems, see `GameTime`.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
+pub struct Simulation;
+
+/// Time as seen by the gameplay systems. Frame time is accumulated and consumed in fixed steps,
+/// so every simulation step advances the game by exactly `delta()`.
+#[derive(Debug)]
+pub struct GameTime {
+    step: Duration,
+    accumulator: Duration,
+    lockstep: bool,
+    tick: u64,
+}
+
+impl GameTime {
+    pub fn new(step: Duration) -> Self {
+        Self {
+            step,
+            accumulator: Duration::from_secs(0),
+            lockstep: false,
+            tick: 0,
+        }
+    }
+
+    /// Runs exactly one step per frame, no matter how long the frame took.
+    pub fn lockstep(step: Duration) -> Self {
+        Self {
+            lockstep: true,
+            ..Self::new(step)
+        }
+    }
+
+    /// Number of the current step since the game started, counting from 1.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    pub fn restart(&mut self) {
+        self.accumulator = Duration::from_secs(0);
+        self.tick = 0;
+    }
+
+    /// Drops the time accumulated for the steps still due this frame, once the game stops running.
+    pub fn stop(&mut self) {
+        self.accumulator = Duration::from_secs(0);
+    }
+
+    pub fn delta(&self) -> Duration {
+        self.step
+    }
+
+    pub fn delta_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    pub fn accumulate(&mut self, frame_delta: Duration) {
+        let frame_delta = if self.lockstep { self.step } else { frame_delta };
+        self.accumulator = (self.accumulator + frame_delta).min(self.step * MAX_STEPS_PER_FRAME);
+    }
+
+    pub fn consume_step(&mut self) -> bool {
+        if self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            self.tick += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// How far the accumulated time is into the next step, from 0 to 1.
+    pub fn overstep(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+}
+
+impl Default for GameTime {
+    fn default() -> Self {
+        Self::new(Duration::from_secs_f64(SIMULATION_STEP))
+    }
+}
+
+/// Keeps the countdown of a timer picking random intervals within a new interval range.
+pub fn clamp_interval(timer: &mut Timer, min_interval: f32, max_interval: f32) {
+    let interval = timer.duration().as_secs_f32();
+    timer.set_duration(Duration::from_secs_f32(interval.max(min_interval).min(max_interval)));
 }
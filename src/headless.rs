@@ -0,0 +1,120 @@
+use crate::awards::AwardMaterials;
+use crate::game::{Game, GameState, GameTime};
+use crate::player::{Player, PlayerInput};
+use crate::random::GameRng;
+use crate::replay::Replay;
+use crate::systems::replay::{ReplayPlayer, ReplayRecorder};
+use crate::systems::plugins::GameplayPlugin;
+use crate::tuning::Tuning;
+
+use bevy::app::App;
+use bevy::asset::AssetPlugin;
+use bevy::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Runs the gameplay systems without a window or renderer, advancing the simulation by exactly
+/// one fixed step on every tick.
+pub struct HeadlessGame {
+    app: App,
+    player: Entity,
+}
+
+impl HeadlessGame {
+    pub fn new(step: Duration, seed: Option<u64>, tuning: Tuning) -> Self {
+        Self::build(step, seed, tuning, None)
+    }
+
+    /// Plays the replay back, its inputs take over the ones given with `set_input`.
+    pub fn from_replay(replay: Replay) -> Self {
+        let header = replay.header.clone();
+        Self::build(header.step, Some(header.seed), header.tuning, Some(replay))
+    }
+
+    fn build(step: Duration, seed: Option<u64>, tuning: Tuning, replay: Option<Replay>) -> Self {
+        let mut builder = App::build();
+        builder
+            .insert_resource(GameTime::lockstep(step))
+            .insert_resource(GameRng::new(seed))
+            .insert_resource(tuning)
+            .insert_resource(AwardMaterials {
+                health: Handle::default(),
+            })
+            .add_plugins(MinimalPlugins)
+            .add_plugin(AssetPlugin)
+            .add_asset::<ColorMaterial>()
+            .add_state(GameState::Starting)
+            .add_plugin(GameplayPlugin);
+
+        if let Some(replay) = replay {
+            builder.insert_resource(ReplayPlayer::new(replay));
+        }
+
+        let mut app = std::mem::take(&mut builder.app);
+        app.update();
+
+        let player = app
+            .world
+            .query_filtered::<Entity, With<Player>>()
+            .iter(&app.world)
+            .next()
+            .expect("player is spawned on startup");
+
+        Self { app, player }
+    }
+
+    /// Records the runs into a replay file saved when each of them is over, like `--record`.
+    pub fn record(&mut self, path: PathBuf) {
+        let world = &mut self.app.world;
+        let mut recorder = ReplayRecorder::new(path);
+        recorder.start(
+            world.get_resource::<GameRng>().unwrap(),
+            world.get_resource::<GameTime>().unwrap(),
+            world.get_resource::<Tuning>().unwrap(),
+        );
+        world.insert_resource(recorder);
+    }
+
+    /// Holds the given controls for the following ticks.
+    pub fn set_input(&mut self, input: PlayerInput) {
+        *self.app.world.get_resource_mut::<PlayerInput>().unwrap() = input;
+    }
+
+    pub fn tick(&mut self) {
+        self.app.update();
+    }
+
+    /// Ticks until the game is over or `max_ticks` is reached, returns the number of ticks run.
+    pub fn run_until_game_over(&mut self, max_ticks: u64) -> u64 {
+        let mut ticks = 0;
+        while ticks < max_ticks && *self.state() != GameState::GameOver {
+            self.tick();
+            ticks += 1;
+        }
+        ticks
+    }
+
+    pub fn state(&self) -> &GameState {
+        self.app
+            .world
+            .get_resource::<State<GameState>>()
+            .unwrap()
+            .current()
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.app.world.get_resource::<GameRng>().unwrap().seed()
+    }
+
+    pub fn game(&self) -> &Game {
+        self.app.world.get_resource::<Game>().unwrap()
+    }
+
+    pub fn player(&self) -> &Player {
+        self.app.world.get::<Player>(self.player).unwrap()
+    }
+
+    pub fn player_transform(&self) -> &Transform {
+        self.app.world.get::<Transform>(self.player).unwrap()
+    }
+}
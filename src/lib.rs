@@ -0,0 +1,83 @@
+pub mod awards;
+pub mod broadphase;
+pub mod effects;
+pub mod enemies;
+pub mod game;
+pub mod headless;
+pub mod pits;
+pub mod platforms;
+pub mod player;
+pub mod random;
+pub mod records;
+pub mod replay;
+pub mod shapes;
+pub mod systems;
+pub mod tuning;
+pub mod world;
+
+use crate::awards::AwardMaterials;
+use crate::game::GameState;
+use crate::systems::plugins::*;
+
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+/// The whole game on top of `DefaultPlugins`. The window is expected to be
+/// `world::SCREEN_WIDTH` by `world::SCREEN_HEIGHT`.
+pub struct JumpOrDiePlugin;
+
+impl Plugin for JumpOrDiePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_state(GameState::StartMenu)
+            .add_plugin(GameplayPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(HudPlugin)
+            .add_plugin(HotReloadPlugin)
+            .add_plugin(RecordsPlugin)
+            .add_startup_system(setup.system())
+            .add_system_set(
+                SystemSet::on_update(GameState::Running)
+                    .with_system(systems::visual_effects::run_visual_effects.system()),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                systems::interpolation::interpolate_translations
+                    .system()
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::Last,
+                systems::interpolation::restore_translations.system(),
+            );
+
+        #[cfg(feature = "debug")]
+        {
+            app.add_plugin(DebugPlugin);
+        }
+    }
+}
+
+fn setup(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    commands.insert_resource(ClearColor(Color::WHITE));
+    commands.insert_resource(AwardMaterials {
+        health: materials.add(asset_server.load("sprites/health.png").into()),
+    });
+
+    commands.spawn_bundle(UiCameraBundle::default());
+    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+
+    commands.spawn_bundle(SpriteBundle {
+        sprite: Sprite::new(Vec2::new(world::SCREEN_WIDTH, world::SCREEN_HEIGHT)),
+        material: materials.add(Color::BLACK.into()),
+        transform: Transform::from_translation(Vec3::new(
+            0.0,
+            -world::SCREEN_HEIGHT / 2.0,
+            0.0,
+        )),
+        ..Default::default()
+    });
+}
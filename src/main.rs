@@ -1,22 +1,35 @@
-mod awards;
-mod effects;
-mod enemies;
-mod game;
-mod player;
-mod systems;
-mod world;
-
-use crate::awards::AwardTimer;
-use crate::effects::{ActiveEffects, EntityEffects, VisualEffects};
-use crate::enemies::SpawnTimer;
-use crate::game::{Game, GameEntity, GameState};
-use crate::player::{Player, PlayerEvent};
-use crate::systems::plugins::*;
-use crate::world::{AffectedByGravity, Gravity, Velocity};
+use jump_or_die::game::{self, GameTime};
+use jump_or_die::headless::HeadlessGame;
+use jump_or_die::random::GameRng;
+use jump_or_die::replay::Replay;
+use jump_or_die::systems::hot_reload::TuningWatcher;
+use jump_or_die::systems::replay::{ReplayPlayer, ReplayRecorder};
+use jump_or_die::tuning::{self, Tuning};
+use jump_or_die::world;
+use jump_or_die::JumpOrDiePlugin;
 
 use bevy::prelude::*;
+use std::fmt::Display;
+use std::path::PathBuf;
+use std::time::Duration;
 
 fn main() {
+    let seed = arg_value("--seed").map(|seed| {
+        or_exit(seed.parse::<u64>().map_err(|err| format!("invalid seed {}: {}", seed, err)))
+    });
+    let replay = arg_value("--replay").map(|path| or_exit(Replay::load(path)));
+    let record_path = arg_value("--record");
+    let tuning_path = tuning_path(arg_value("--tuning"));
+    let tuning = match &tuning_path {
+        Some(path) => or_exit(Tuning::load(path)),
+        None => Tuning::default(),
+    };
+
+    if std::env::args().any(|arg| arg == "--headless") {
+        run_headless(seed, tuning, replay, record_path);
+        return;
+    }
+
     let mut app = App::build();
     app.insert_resource(WindowDescriptor {
         width: world::SCREEN_WIDTH,
@@ -24,78 +37,77 @@ fn main() {
         resizable: false,
         ..Default::default()
     })
-    .add_plugins(DefaultPlugins)
-    .add_state(GameState::StartMenu)
-    .add_plugin(InputPlugin)
-    .add_plugin(HudPlugin)
-    .add_event::<PlayerEvent>()
-    .add_startup_system(setup.system())
-    .add_system_set(
-        SystemSet::on_enter(GameState::Starting)
-            .with_system(systems::gameplay::start_game.system()),
-    )
-    .add_system_set(
-        SystemSet::on_update(GameState::Running)
-            .with_system(systems::spawning::drop_enemies.system())
-            .with_system(systems::spawning::spawn_health.system())
-            .with_system(systems::gameplay::apply_effects.system())
-            .with_system(systems::gameplay::cleanup_effects.system())
-            .with_system(systems::visual_effects::run_visual_effects.system())
-            .with_system(systems::spawning::spawn_new_enemy.system())
-            .with_system(systems::gameplay::random_enemy_jump.system())
-            .with_system(systems::physics::movement.system())
-            .with_system(systems::physics::gravity.system())
-            .with_system(systems::physics::collisions.system())
-            .with_system(systems::events::player_events.system()),
-    );
+    .add_plugins(DefaultPlugins);
+
+    match replay {
+        Some(replay) => {
+            app.insert_resource(GameRng::new(Some(replay.header.seed)))
+                .insert_resource(GameTime::new(replay.header.step))
+                .insert_resource(replay.header.tuning.clone())
+                .insert_resource(ReplayPlayer::new(replay));
+        }
+        None => {
+            app.insert_resource(GameRng::new(seed)).insert_resource(tuning);
+            // Replays only keep the tuning a run started with, so it can't change while recording.
+            if let Some(path) = tuning_path.filter(|_| record_path.is_none()) {
+                app.insert_resource(TuningWatcher::new(path));
+            }
+        }
+    }
 
-    #[cfg(feature = "debug")]
-    {
-        app.add_plugin(DebugPlugin);
+    if let Some(path) = record_path {
+        app.insert_resource(ReplayRecorder::new(PathBuf::from(path)));
     }
 
-    app.run();
+    app.add_plugin(JumpOrDiePlugin).run();
 }
 
-fn setup(mut commands: Commands, mut materials: ResMut<Assets<ColorMaterial>>) {
-    commands.insert_resource(ClearColor(Color::WHITE));
-    commands.insert_resource(Game::default());
-    commands.insert_resource(Gravity::default());
-    commands.insert_resource(AwardTimer::new(5.0, 15.0));
-    commands.insert_resource(SpawnTimer {
-        timer: Timer::from_seconds(3.0, true),
-    });
+fn arg_value(name: &str) -> Option<String> {
+    let mut args = std::env::args().skip_while(|arg| arg != name);
+    args.next().and(args.next())
+}
 
-    commands.spawn_bundle(UiCameraBundle::default());
-    commands.spawn_bundle(OrthographicCameraBundle::new_2d());
+fn or_exit<T, E: Display>(result: Result<T, E>) -> T {
+    result.unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    })
+}
 
-    commands.spawn_bundle(SpriteBundle {
-        sprite: Sprite::new(Vec2::new(world::SCREEN_WIDTH, world::SCREEN_HEIGHT)),
-        material: materials.add(Color::BLACK.into()),
-        transform: Transform::from_translation(Vec3::new(
-            0.0,
-            -world::SCREEN_HEIGHT / 2.0,
-            0.0,
-        )),
-        ..Default::default()
-    });
+/// The given tuning file, or the default one if it exists.
+fn tuning_path(path: Option<String>) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(PathBuf::from(path)),
+        None => Some(PathBuf::from(tuning::DEFAULT_PATH)).filter(|path| path.exists()),
+    }
+}
+
+fn run_headless(
+    seed: Option<u64>,
+    tuning: Tuning,
+    replay: Option<Replay>,
+    record_path: Option<String>,
+) {
+    const MAX_TICKS: u64 = 60 * 60 * 10;
 
-    commands.spawn()
-        .insert(Player::new())
-        .insert(GameEntity)
-        .insert(EntityEffects::default())
-        .insert(ActiveEffects::new())
-        .insert(VisualEffects::new())
-        .insert(Velocity::default())
-        .insert(AffectedByGravity)
-        .insert_bundle(SpriteBundle {
-            sprite: Sprite::new(Vec2::new(player::WIDTH, player::HEIGHT)),
-            material: materials.add(Color::BLACK.into()),
-            transform: Transform::from_translation(Vec3::new(
-                player::INITIAL_POSITION_X,
-                player::INITIAL_POSITION_Y,
-                0.0,
-            )),
-            ..Default::default()
-        });
+    let mut game = match replay {
+        Some(replay) => HeadlessGame::from_replay(replay),
+        None => HeadlessGame::new(
+            Duration::from_secs_f64(game::SIMULATION_STEP),
+            seed,
+            tuning,
+        ),
+    };
+    if let Some(path) = record_path {
+        game.record(PathBuf::from(path));
+    }
+    let ticks = game.run_until_game_over(MAX_TICKS);
+
+    println!(
+        "Seed: {}\nTicks: {}\nGame: {:#?}\nPlayer: {:#?}",
+        game.seed(),
+        ticks,
+        game.game(),
+        game.player()
+    );
 }
@@ -0,0 +1,13 @@
+use crate::world;
+
+/// Chance that an obstacle is a gap in the ground instead of an enemy.
+pub const CHANCE: f32 = 0.25;
+
+pub const MIN_WIDTH: f32 = 80.0;
+pub const MAX_WIDTH: f32 = 160.0;
+
+/// Pits reach from the ground down to the bottom of the screen.
+pub const DEPTH: f32 = world::SCREEN_HEIGHT / 2.0;
+
+/// Gap in the ground. Entities affected by gravity fall through it out of the screen.
+pub struct Pit;
@@ -0,0 +1,22 @@
+use crate::world;
+use bevy::core::Timer;
+
+pub const WIDTH: f32 = 200.0;
+pub const HEIGHT: f32 = 20.0;
+
+pub const INITIAL_POSITION_X: f32 = (world::SCREEN_WIDTH + WIDTH) / 2.0;
+
+/// Range for the height of a platform's top above the ground.
+pub const MIN_HEIGHT: f32 = 50.0;
+pub const MAX_HEIGHT: f32 = 100.0;
+
+pub const INITIAL_SPAWN_DELAY: f32 = 5.0;
+pub const MIN_SPAWN_INTERVAL: f32 = 3.0;
+pub const MAX_SPAWN_INTERVAL: f32 = 6.0;
+
+/// One-way platform: can be jumped up through and landed on from above.
+pub struct Platform;
+
+pub struct PlatformTimer {
+    pub timer: Timer,
+}
@@ -1,21 +1,63 @@
 use crate::awards::Award;
 use crate::world;
-use crate::world::Velocity;
+use crate::shapes::Shape;
+use crate::world::{Hurtbox, Knockback, Velocity};
+use bevy::math::Vec2;
+use serde::{Deserialize, Serialize};
 
 pub const WIDTH: f32 = 50.0;
 pub const HEIGHT: f32 = 50.0;
+/// The player gets hurt by a capsule narrower than the sprite.
+pub const HURTBOX_RADIUS: f32 = 18.0;
+pub const HURTBOX_HEIGHT: f32 = 46.0;
+/// Height of the player's hurtbox and sprite while crouching or sliding.
+pub const CROUCH_HEIGHT: f32 = 26.0;
 
 pub const INITIAL_POSITION_X: f32 = -world::SCREEN_WIDTH / 2.0 + 128.0;
 pub const INITIAL_POSITION_Y: f32 = HEIGHT / 2.0;
 
 pub const VELOCITY_ON_JUMP: f32 = 600.0;
 pub const MOVEMENT_VELOCITY: f32 = 400.0;
+pub const GROUND_ACCELERATION: f32 = 4000.0;
+pub const GROUND_DECELERATION: f32 = 5000.0;
+pub const AIR_ACCELERATION: f32 = 1500.0;
+pub const AIR_DECELERATION: f32 = 500.0;
+pub const VELOCITY_ON_STOMP: f32 = 450.0;
+
+/// Share of the upward velocity kept when jump is released early.
+pub const JUMP_CUT: f32 = 0.5;
+pub const COYOTE_TIME: f32 = 0.1;
+pub const JUMP_BUFFER_TIME: f32 = 0.1;
+
+pub const DOUBLE_JUMP_VELOCITY: f32 = 500.0;
+pub const DOUBLE_JUMP_CHARGES: u32 = 1;
+pub const AIR_DASH_BOOST: f32 = 3.0;
+pub const AIR_DASH_DURATION: f32 = 0.15;
+pub const AIR_DASH_CHARGES: u32 = 1;
+pub const SLIDE_VELOCITY: f32 = 600.0;
+pub const SLIDE_TIME: f32 = 0.4;
+
+pub const GROUND_POUND_VELOCITY: f32 = 1500.0;
+pub const GROUND_POUND_COOLDOWN: f32 = 1.0;
+/// Ground enemies this close to a ground pound landing are destroyed, further out up to
+/// `SHOCKWAVE_RADIUS` they are pushed away.
+pub const SHOCKWAVE_KILL_RADIUS: f32 = 80.0;
+pub const SHOCKWAVE_RADIUS: f32 = 160.0;
+/// How high above the landing spot the shockwave reaches.
+pub const SHOCKWAVE_HEIGHT: f32 = 30.0;
+pub const SHOCKWAVE_KNOCKBACK_SPEED: f32 = 500.0;
+pub const SHOCKWAVE_KNOCKBACK_LIFT: f32 = 400.0;
+pub const SHOCKWAVE_KNOCKBACK_TIME: f32 = 0.3;
 
 #[derive(Debug)]
 pub struct Player {
     pub movement_state: PlayerMovementState,
     pub health: u8,
     pub max_health: u8,
+    pub stomp_combo: u32,
+    /// Down is held on the ground.
+    pub crouching: bool,
+    pub slide_time_left: f32,
 }
 
 impl Player {
@@ -24,21 +66,175 @@ impl Player {
             movement_state: PlayerMovementState::Staying,
             health: 3,
             max_health: 3,
+            stomp_combo: 0,
+            crouching: false,
+            slide_time_left: 0.0,
+        }
+    }
+
+    pub fn is_on_ground(&self) -> bool {
+        matches!(
+            self.movement_state,
+            PlayerMovementState::Staying
+                | PlayerMovementState::Running
+                | PlayerMovementState::Crouching
+                | PlayerMovementState::Sliding
+        )
+    }
+
+    pub fn is_crouched(&self) -> bool {
+        matches!(
+            self.movement_state,
+            PlayerMovementState::Crouching | PlayerMovementState::Sliding
+        )
+    }
+
+    pub fn hurtbox(&self) -> Hurtbox {
+        if self.is_crouched() {
+            Hurtbox(
+                Shape::capsule(HURTBOX_RADIUS, CROUCH_HEIGHT)
+                    .with_offset(Vec2::new(0.0, (CROUCH_HEIGHT - HEIGHT) / 2.0)),
+            )
+        } else {
+            Hurtbox(Shape::capsule(HURTBOX_RADIUS, HURTBOX_HEIGHT))
         }
     }
 }
 
+/// Jump input state carried between simulation steps.
+#[derive(Debug, Default)]
+pub struct JumpControl {
+    /// Whether jump was held on the previous step, to tell new presses apart.
+    pub held: bool,
+    /// Rising from a jump that is cut short when jump is released.
+    pub rising: bool,
+    /// Time left to jump after leaving the ground.
+    pub coyote_time_left: f32,
+    /// Time left for a press made in the air to fire on landing.
+    pub buffer_time_left: f32,
+}
+
+/// Ground pound state carried between simulation steps.
+#[derive(Debug, Default)]
+pub struct GroundPound {
+    /// Whether down was held on the previous step, to tell new presses apart.
+    pub down_held: bool,
+    /// Slamming down, a shockwave goes off on landing.
+    pub slamming: bool,
+    pub cooldown_left: f32,
+}
+
+/// Goes off where a ground pound lands and lasts a single step.
+pub struct Shockwave;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Ability {
+    DoubleJump,
+    AirDash,
+}
+
+impl Ability {
+    pub const ALL: [Ability; 2] = [Ability::DoubleJump, Ability::AirDash];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ability::DoubleJump => "Double jump",
+            Ability::AirDash => "Air dash",
+        }
+    }
+}
+
+/// Uses of an ability left until the player is back on the ground.
+#[derive(Debug, Default)]
+pub struct AbilityCharges {
+    pub unlocked: bool,
+    pub max: u32,
+    pub left: u32,
+}
+
+impl AbilityCharges {
+    pub fn refill(&mut self) {
+        self.left = self.max;
+    }
+
+    pub fn use_charge(&mut self) -> bool {
+        if self.unlocked && self.left > 0 {
+            self.left -= 1;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Moves available in the air. Locked until picked up or given from the start by tuning.
+#[derive(Debug, Default)]
+pub struct Abilities {
+    pub double_jump: AbilityCharges,
+    pub air_dash: AbilityCharges,
+}
+
+impl Abilities {
+    pub fn get(&self, ability: Ability) -> &AbilityCharges {
+        match ability {
+            Ability::DoubleJump => &self.double_jump,
+            Ability::AirDash => &self.air_dash,
+        }
+    }
+
+    pub fn get_mut(&mut self, ability: Ability) -> &mut AbilityCharges {
+        match ability {
+            Ability::DoubleJump => &mut self.double_jump,
+            Ability::AirDash => &mut self.air_dash,
+        }
+    }
+
+    pub fn unlock(&mut self, ability: Ability, charges: u32) {
+        let ability = self.get_mut(ability);
+        ability.unlocked = true;
+        ability.max = charges;
+        ability.refill();
+    }
+
+    pub fn refill(&mut self) {
+        self.double_jump.refill();
+        self.air_dash.refill();
+    }
+}
+
 #[derive(Debug, PartialEq, Eq)]
 pub enum PlayerMovementState {
     Staying,
     Running,
     Jumping,
     Falling,
+    Crouching,
+    Sliding,
+}
+
+/// Player controls sampled from the keyboard, applied on the next simulation step.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlayerInput {
+    pub jump: bool,
+    pub left: bool,
+    pub right: bool,
+    pub down: bool,
+    pub dash: bool,
 }
 
 pub enum PlayerEvent {
-    Hit,
+    /// Hit by something pushing the player `away` from it, if it has a `Knockback`.
+    Hit {
+        knockback: Option<Knockback>,
+        away: Vec2,
+    },
+    Stomp,
+    Fall,
     Award(Award),
+    /// A ground pound landed, destroying this many enemies.
+    GroundPound {
+        destroyed: u32,
+    },
 }
 
 pub fn update_movement_state(player: &mut Player, velocity: &Velocity) {
@@ -47,6 +243,10 @@ pub fn update_movement_state(player: &mut Player, velocity: &Velocity) {
         player.movement_state = PlayerMovementState::Jumping;
     } else if v.y < 0.0 {
         player.movement_state = PlayerMovementState::Falling;
+    } else if player.slide_time_left > 0.0 {
+        player.movement_state = PlayerMovementState::Sliding;
+    } else if player.crouching {
+        player.movement_state = PlayerMovementState::Crouching;
     } else if v.x != 0.0 {
         player.movement_state = PlayerMovementState::Running
     } else {
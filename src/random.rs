@@ -0,0 +1,62 @@
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// The only source of randomness for gameplay. Every run is reseeded, either with the fixed
+/// seed, or with a fresh one.
+pub struct GameRng {
+    fixed_seed: Option<u64>,
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub fn new(fixed_seed: Option<u64>) -> Self {
+        let seed = fixed_seed.unwrap_or_else(new_seed);
+        Self {
+            fixed_seed,
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn fixed_seed(&self) -> Option<u64> {
+        self.fixed_seed
+    }
+
+    pub fn reseed(&mut self) {
+        self.seed = self.fixed_seed.unwrap_or_else(new_seed);
+        self.rng = StdRng::seed_from_u64(self.seed);
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
+
+fn new_seed() -> u64 {
+    StdRng::from_entropy().next_u64()
+}
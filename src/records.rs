@@ -0,0 +1,99 @@
+use chrono::NaiveDate;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// Per-user directory for everything the game keeps between sessions.
+pub fn data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("jump-or-die"))
+}
+
+pub(crate) fn read<T: DeserializeOwned>(file_name: &str) -> io::Result<Option<T>> {
+    let path = match data_dir() {
+        Some(dir) => dir.join(file_name),
+        None => return Ok(None),
+    };
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    ron::de::from_str(&contents)
+        .map(Some)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+pub(crate) fn write<T: Serialize>(file_name: &str, value: &T) -> io::Result<()> {
+    let dir = data_dir()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    let contents = ron::ser::to_string_pretty(value, Default::default())
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    fs::create_dir_all(&dir)?;
+    fs::write(dir.join(file_name), contents)
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct BestScore {
+    pub score: f32,
+    pub date: Option<NaiveDate>,
+}
+
+impl BestScore {
+    const FILE_NAME: &'static str = "best_score.ron";
+
+    pub fn load() -> io::Result<Self> {
+        read(Self::FILE_NAME).map(Option::unwrap_or_default)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        write(Self::FILE_NAME, self)
+    }
+}
+
+pub const LEADERBOARD_SIZE: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LeaderboardEntry {
+    pub name: String,
+    pub score: f32,
+    pub date: NaiveDate,
+    pub time_survived: f32,
+    pub seed: Option<u64>,
+}
+
+/// Best runs, highest score first.
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Leaderboard {
+    pub entries: Vec<LeaderboardEntry>,
+}
+
+impl Leaderboard {
+    const FILE_NAME: &'static str = "leaderboard.ron";
+
+    pub fn load() -> io::Result<Self> {
+        read(Self::FILE_NAME).map(Option::unwrap_or_default)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        write(Self::FILE_NAME, self)
+    }
+
+    pub fn qualifies(&self, score: f32) -> bool {
+        score > 0.0
+            && (self.entries.len() < LEADERBOARD_SIZE
+                || self.entries.iter().any(|entry| score > entry.score))
+    }
+
+    pub fn insert(&mut self, entry: LeaderboardEntry) {
+        let position = self
+            .entries
+            .iter()
+            .position(|other| entry.score > other.score)
+            .unwrap_or_else(|| self.entries.len());
+        self.entries.insert(position, entry);
+        self.entries.truncate(LEADERBOARD_SIZE);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::player::PlayerInput;
+use crate::tuning::Tuning;
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const FORMAT_VERSION: u32 = 11;
+
+/// Everything a run depends on besides the inputs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayHeader {
+    pub version: u32,
+    pub seed: u64,
+    pub step: Duration,
+    pub tuning: Tuning,
+}
+
+/// Player inputs of a single run, stored as the ticks they changed on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    pub header: ReplayHeader,
+    pub inputs: Vec<(u64, PlayerInput)>,
+}
+
+#[derive(Deserialize)]
+struct Versioned {
+    header: VersionedHeader,
+}
+
+#[derive(Deserialize)]
+struct VersionedHeader {
+    version: u32,
+}
+
+impl Replay {
+    pub fn new(seed: u64, step: Duration, tuning: Tuning) -> Self {
+        Self {
+            header: ReplayHeader {
+                version: FORMAT_VERSION,
+                seed,
+                step,
+                tuning,
+            },
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|err| ReplayError::Io(path.to_owned(), err))?;
+
+        let versioned: Versioned = ron::de::from_str(&contents).map_err(ReplayError::Parse)?;
+        if versioned.header.version != FORMAT_VERSION {
+            return Err(ReplayError::Version(versioned.header.version));
+        }
+
+        ron::de::from_str(&contents).map_err(ReplayError::Parse)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let path = path.as_ref();
+        let contents = ron::ser::to_string(self).map_err(ReplayError::Parse)?;
+        fs::write(path, contents).map_err(|err| ReplayError::Io(path.to_owned(), err))
+    }
+
+    /// Records the input applied on the given tick.
+    pub fn record(&mut self, tick: u64, input: PlayerInput) {
+        let last_input = self.inputs.last().map(|(_, input)| *input).unwrap_or_default();
+        if input != last_input {
+            self.inputs.push((tick, input));
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(PathBuf, std::io::Error),
+    Parse(ron::Error),
+    Version(u32),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(path, err) => write!(f, "replay file {}: {}", path.display(), err),
+            ReplayError::Parse(err) => write!(f, "malformed replay: {}", err),
+            ReplayError::Version(version) => write!(
+                f,
+                "unsupported replay version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ReplayError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_with_version(name: &str, version: u32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!(
+            "jump-or-die-{}-{}.ron",
+            name,
+            std::process::id()
+        ));
+        let mut replay = Replay::new(7, Duration::from_millis(16), Tuning::default());
+        replay.header.version = version;
+        replay.save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn loads_the_current_version() {
+        let path = save_with_version("current-version", FORMAT_VERSION);
+        let loaded = Replay::load(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap().header.seed, 7);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        for &version in &[FORMAT_VERSION - 1, FORMAT_VERSION + 1] {
+            let path = save_with_version("other-version", version);
+            let loaded = Replay::load(&path);
+            fs::remove_file(&path).unwrap();
+            match loaded {
+                Err(ReplayError::Version(loaded_version)) => assert_eq!(loaded_version, version),
+                other => panic!("version {} loaded as {:?}", version, other),
+            }
+        }
+    }
+}
@@ -0,0 +1,334 @@
+use crate::broadphase;
+use bevy::math::Vec2;
+
+/// Collision shape of an entity, independent of its sprite. The offset moves it away from the
+/// entity's translation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Shape {
+    Aabb { offset: Vec2, size: Vec2 },
+    Circle { offset: Vec2, radius: f32 },
+    /// Upright capsule `height` tall in total, with half circles of `radius` at both ends.
+    Capsule { offset: Vec2, radius: f32, height: f32 },
+}
+
+impl Shape {
+    pub fn aabb(size: Vec2) -> Self {
+        Shape::Aabb {
+            offset: Vec2::ZERO,
+            size,
+        }
+    }
+
+    pub fn circle(radius: f32) -> Self {
+        Shape::Circle {
+            offset: Vec2::ZERO,
+            radius,
+        }
+    }
+
+    pub fn capsule(radius: f32, height: f32) -> Self {
+        Shape::Capsule {
+            offset: Vec2::ZERO,
+            radius,
+            height,
+        }
+    }
+
+    pub fn with_offset(mut self, new_offset: Vec2) -> Self {
+        match &mut self {
+            Shape::Aabb { offset, .. }
+            | Shape::Circle { offset, .. }
+            | Shape::Capsule { offset, .. } => *offset = new_offset,
+        }
+        self
+    }
+
+    pub fn offset(&self) -> Vec2 {
+        match *self {
+            Shape::Aabb { offset, .. }
+            | Shape::Circle { offset, .. }
+            | Shape::Capsule { offset, .. } => offset,
+        }
+    }
+
+    /// Size of the box around the shape.
+    pub fn size(&self) -> Vec2 {
+        let (half_size, radius) = self.rounded_box();
+        (half_size + Vec2::splat(radius)) * 2.0
+    }
+
+    /// Box covering the shape moving from `start` to `end`.
+    pub fn swept_bounds(&self, start: Vec2, end: Vec2) -> (Vec2, Vec2) {
+        broadphase::swept_bounds(start + self.offset(), end + self.offset(), self.size())
+    }
+
+    /// Every shape is a box with rounded corners: the half size of the box before rounding and
+    /// the rounding radius.
+    fn rounded_box(&self) -> (Vec2, f32) {
+        match *self {
+            Shape::Aabb { size, .. } => (size / 2.0, 0.0),
+            Shape::Circle { radius, .. } => (Vec2::ZERO, radius),
+            Shape::Capsule { radius, height, .. } => {
+                (Vec2::new(0.0, (height / 2.0 - radius).max(0.0)), radius)
+            }
+        }
+    }
+}
+
+/// Normal pointing from `b` towards `a` where two shapes moving from their start to their end
+/// positions touch. Catches shapes that passed through each other during the step, no matter how
+/// fast they move, with the normal of the side they came into contact on.
+pub fn swept_contact(
+    a: &Shape,
+    a_start: Vec2,
+    a_end: Vec2,
+    b: &Shape,
+    b_start: Vec2,
+    b_end: Vec2,
+) -> Option<Vec2> {
+    // Sweep a point against both shapes added together.
+    let (a_half_size, a_radius) = a.rounded_box();
+    let (b_half_size, b_radius) = b.rounded_box();
+    let half_size = a_half_size + b_half_size;
+    let radius = a_radius + b_radius;
+    let start = (a_start + a.offset()) - (b_start + b.offset());
+    let end = (a_end + a.offset()) - (b_end + b.offset());
+
+    if contains(start, half_size, radius) {
+        return if contains(end, half_size, radius) {
+            Some(resting_normal(end, half_size))
+        } else {
+            None
+        };
+    }
+
+    let delta = end - start;
+    let outer_half_size = half_size + Vec2::splat(radius);
+    let (x_entry, x_exit) = sweep_axis(start.x, delta.x, outer_half_size.x)?;
+    let (y_entry, y_exit) = sweep_axis(start.y, delta.y, outer_half_size.y)?;
+
+    let entry = x_entry.max(y_entry);
+    let exit = x_exit.min(y_exit);
+    if entry >= exit || entry < 0.0 || entry > 1.0 {
+        return None;
+    }
+
+    let point = start + delta * entry;
+    if radius > 0.0 && point.x.abs() > half_size.x && point.y.abs() > half_size.y {
+        // Came in next to a rounded corner, it may still miss it.
+        let corner = corner_towards(point, half_size);
+        let entry = circle_entry(start - corner, delta, radius)?;
+        return Some((start + delta * entry - corner).normalize());
+    }
+
+    if x_entry > y_entry {
+        Some(Vec2::new(-delta.x.signum(), 0.0))
+    } else {
+        Some(Vec2::new(0.0, -delta.y.signum()))
+    }
+}
+
+fn contains(point: Vec2, half_size: Vec2, radius: f32) -> bool {
+    let gap = Vec2::new(point.x.abs(), point.y.abs()) - half_size;
+    if radius == 0.0 {
+        gap.x < 0.0 && gap.y < 0.0
+    } else {
+        gap.max(Vec2::ZERO).length_squared() < radius * radius
+    }
+}
+
+/// Normal of the side an overlapping point is least deep behind.
+fn resting_normal(point: Vec2, half_size: Vec2) -> Vec2 {
+    let gap = Vec2::new(point.x.abs(), point.y.abs()) - half_size;
+    if gap.x > 0.0 && gap.y > 0.0 {
+        (point - corner_towards(point, half_size)).normalize()
+    } else if gap.x > gap.y {
+        Vec2::new(1.0f32.copysign(point.x), 0.0)
+    } else {
+        Vec2::new(0.0, 1.0f32.copysign(point.y))
+    }
+}
+
+fn corner_towards(point: Vec2, half_size: Vec2) -> Vec2 {
+    Vec2::new(
+        half_size.x.copysign(point.x),
+        half_size.y.copysign(point.y),
+    )
+}
+
+/// Fractions of the step at which an offset moving by delta enters and leaves `-half..half`.
+fn sweep_axis(offset: f32, delta: f32, half: f32) -> Option<(f32, f32)> {
+    if delta == 0.0 {
+        if offset.abs() < half {
+            Some((f32::NEG_INFINITY, f32::INFINITY))
+        } else {
+            None
+        }
+    } else {
+        let t1 = (-half - offset) / delta;
+        let t2 = (half - offset) / delta;
+        Some((t1.min(t2), t1.max(t2)))
+    }
+}
+
+/// Fraction of the step at which an offset outside of a circle around the origin moving by delta
+/// enters it.
+fn circle_entry(offset: Vec2, delta: Vec2, radius: f32) -> Option<f32> {
+    let a = delta.length_squared();
+    if a == 0.0 {
+        return None;
+    }
+    let b = 2.0 * offset.dot(delta);
+    let c = offset.length_squared() - radius * radius;
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return None;
+    }
+
+    let entry = (-b - discriminant.sqrt()) / (2.0 * a);
+    if (0.0..=1.0).contains(&entry) {
+        Some(entry)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_normal(contact: Option<Vec2>, expected: Vec2) {
+        let normal = contact.expect("shapes should touch");
+        assert!(
+            (normal - expected).length() < 1e-5,
+            "normal {:?}, expected {:?}",
+            normal,
+            expected
+        );
+    }
+
+    #[test]
+    fn box_falling_onto_box() {
+        let a = Shape::aabb(Vec2::new(20.0, 20.0));
+        let b = Shape::aabb(Vec2::new(50.0, 20.0));
+        let contact = swept_contact(
+            &a,
+            Vec2::new(0.0, 40.0),
+            Vec2::new(0.0, 10.0),
+            &b,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_normal(contact, Vec2::new(0.0, 1.0));
+    }
+
+    #[test]
+    fn boxes_apart_dont_touch() {
+        let a = Shape::aabb(Vec2::new(20.0, 20.0));
+        let contact = swept_contact(
+            &a,
+            Vec2::new(0.0, 40.0),
+            Vec2::new(30.0, 40.0),
+            &a,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn fast_box_doesnt_tunnel() {
+        let a = Shape::aabb(Vec2::new(10.0, 10.0));
+        let contact = swept_contact(
+            &a,
+            Vec2::new(-200.0, 0.0),
+            Vec2::new(200.0, 0.0),
+            &a,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_normal(contact, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn circle_passing_a_box_corner_misses() {
+        let a = Shape::circle(10.0);
+        let b = Shape::aabb(Vec2::new(50.0, 50.0));
+        // Goes through the box around both shapes, but stays more than the radius away from the
+        // corner.
+        let contact = swept_contact(
+            &a,
+            Vec2::new(80.0, -15.0),
+            Vec2::new(-15.0, 80.0),
+            &b,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn circle_hitting_a_box_corner() {
+        let a = Shape::circle(10.0);
+        let b = Shape::aabb(Vec2::new(50.0, 50.0));
+        let contact = swept_contact(
+            &a,
+            Vec2::new(50.0, 50.0),
+            Vec2::new(20.0, 20.0),
+            &b,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_normal(contact, Vec2::new(1.0, 1.0).normalize());
+    }
+
+    #[test]
+    fn starting_inside_touches_on_the_nearest_side() {
+        let a = Shape::aabb(Vec2::new(20.0, 20.0));
+        let start = Vec2::new(10.0, 0.0);
+        let contact = swept_contact(&a, start, start, &a, Vec2::ZERO, Vec2::ZERO);
+        assert_normal(contact, Vec2::new(1.0, 0.0));
+    }
+
+    #[test]
+    fn starting_inside_and_moving_out_doesnt_touch() {
+        let a = Shape::aabb(Vec2::new(20.0, 20.0));
+        let contact = swept_contact(
+            &a,
+            Vec2::new(10.0, 0.0),
+            Vec2::new(50.0, 0.0),
+            &a,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_eq!(contact, None);
+    }
+
+    #[test]
+    fn normal_points_from_b_to_a() {
+        let a = Shape::capsule(10.0, 40.0);
+        let b = Shape::aabb(Vec2::new(20.0, 20.0));
+        let a_start = Vec2::new(60.0, 0.0);
+        let a_end = Vec2::new(10.0, 0.0);
+
+        let contact = swept_contact(&a, a_start, a_end, &b, Vec2::ZERO, Vec2::ZERO);
+        assert_normal(contact, Vec2::new(1.0, 0.0));
+        let contact = swept_contact(&b, Vec2::ZERO, Vec2::ZERO, &a, a_start, a_end);
+        assert_normal(contact, Vec2::new(-1.0, 0.0));
+    }
+
+    #[test]
+    fn offsets_move_the_shapes() {
+        let a = Shape::circle(10.0).with_offset(Vec2::new(0.0, -30.0));
+        let b = Shape::aabb(Vec2::new(20.0, 20.0));
+        let contact = swept_contact(
+            &a,
+            Vec2::new(0.0, 80.0),
+            Vec2::new(0.0, 40.0),
+            &b,
+            Vec2::ZERO,
+            Vec2::ZERO,
+        );
+        assert_normal(contact, Vec2::new(0.0, 1.0));
+    }
+}
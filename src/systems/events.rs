@@ -1,19 +1,43 @@
 use crate::awards::Award;
-use crate::effects::{ActiveEffects, Effect, EffectType, PeriodicInvisibility, VisualEffects};
-use crate::game::{Game, GameState};
-use crate::player::{Player, PlayerEvent};
+use crate::effects::{
+    ActiveEffects, Effect, EffectType, EntityEffectDescriptor, EntityEffects, HitStun,
+    PeriodicInvisibility, VisualEffects,
+};
+use crate::game::{Game, GameState, GameTime};
+use crate::player::{Abilities, Player, PlayerEvent};
+use crate::tuning::Tuning;
+use crate::world::{self, Deformation, Knockback, Velocity};
 use bevy::prelude::*;
+use std::time::Duration;
 
 pub fn player_events(
     mut game: ResMut<Game>,
     mut state: ResMut<State<GameState>>,
+    mut game_time: ResMut<GameTime>,
+    tuning: Res<Tuning>,
     mut event_reader: EventReader<PlayerEvent>,
-    mut player_query: Query<(&mut Player, &mut ActiveEffects, &mut VisualEffects)>,
+    mut player_query: Query<(
+        &mut Player,
+        &mut Velocity,
+        &mut EntityEffects,
+        &mut ActiveEffects,
+        &mut VisualEffects,
+        &mut Deformation,
+    )>,
+    mut abilities_query: Query<&mut Abilities>,
 ) {
     for e in event_reader.iter() {
         match e {
-            PlayerEvent::Hit => {
-                for (mut player, mut effects, mut visual_effects) in player_query.iter_mut() {
+            PlayerEvent::Hit { knockback, away } => {
+                for (
+                    mut player,
+                    mut velocity,
+                    mut entity_effects,
+                    mut effects,
+                    mut visual_effects,
+                    mut deformation,
+                ) in player_query.iter_mut()
+                {
                     let is_invulnerable = {
                         let mut is_invulnerable = false;
                         for effect in &effects.effects {
@@ -24,30 +48,127 @@ pub fn player_events(
                         is_invulnerable
                     };
 
-                    if !is_invulnerable && player.health > 0 {
-                        player.health -= 1;
-                        if player.health == 0 {
-                            state.set(GameState::GameOver).unwrap();
-                        } else {
-                            effects.effects.push(Effect::new_invulnerability());
-                            visual_effects
-                                .effects
-                                .push(Box::new(PeriodicInvisibility::new(0.2, 3.0)));
+                    if !is_invulnerable {
+                        take_damage(
+                            &mut player,
+                            &mut effects,
+                            &mut visual_effects,
+                            &mut state,
+                            &mut game_time,
+                        );
+                        if let Some(knockback) = knockback {
+                            knock_back(knockback, *away, &mut velocity, &mut entity_effects);
                         }
+                        deformation.squeeze(world::HIT_DEFORMATION);
                     }
                 }
             }
+            PlayerEvent::Fall => {
+                for (
+                    mut player,
+                    _velocity,
+                    _entity_effects,
+                    mut effects,
+                    mut visual_effects,
+                    _deformation,
+                ) in player_query.iter_mut()
+                {
+                    take_damage(
+                        &mut player,
+                        &mut effects,
+                        &mut visual_effects,
+                        &mut state,
+                        &mut game_time,
+                    );
+                }
+            }
+            PlayerEvent::Stomp => {
+                for (
+                    mut player,
+                    mut velocity,
+                    _entity_effects,
+                    _effects,
+                    _visual_effects,
+                    _deformation,
+                ) in player_query.iter_mut()
+                {
+                    player.stomp_combo += 1;
+                    velocity.set_vertical(tuning.player.stomp_velocity);
+
+                    game.score += tuning.enemies.stomp_score * player.stomp_combo as f32;
+                    game.best_score = game.best_score.max(game.score);
+                }
+            }
+            PlayerEvent::GroundPound { destroyed } => {
+                game.score += tuning.enemies.stomp_score * *destroyed as f32;
+                game.best_score = game.best_score.max(game.score);
+            }
             PlayerEvent::Award(award) => match award {
                 Award::Score(score) => {
                     game.score += score;
                     game.best_score = game.best_score.max(game.score);
                 }
                 Award::Health(health) => {
-                    for (mut player, mut _effects, mut _visual_effects) in player_query.iter_mut() {
+                    for (
+                        mut player,
+                        _velocity,
+                        _entity_effects,
+                        _effects,
+                        _visual_effects,
+                        _deformation,
+                    ) in player_query.iter_mut()
+                    {
                         player.health = (player.health + health).min(player.max_health);
                     }
                 }
+                Award::Ability(ability) => {
+                    for mut abilities in abilities_query.iter_mut() {
+                        abilities.unlock(*ability, tuning.abilities.charges(*ability));
+                    }
+                }
             },
         }
     }
 }
+
+fn take_damage(
+    player: &mut Player,
+    effects: &mut ActiveEffects,
+    visual_effects: &mut VisualEffects,
+    state: &mut State<GameState>,
+    game_time: &mut GameTime,
+) {
+    if player.health == 0 {
+        return;
+    }
+
+    player.health -= 1;
+    if player.health == 0 {
+        state.set(GameState::GameOver).unwrap();
+        // The state only changes after this frame, don't keep playing the steps left in it.
+        game_time.stop();
+    } else {
+        effects.effects.push(Effect::new_invulnerability());
+        visual_effects
+            .effects
+            .push(Box::new(PeriodicInvisibility::new(0.2, 3.0)));
+    }
+}
+
+/// Pushes the player away from what hit them and ignores their input for a moment.
+fn knock_back(
+    knockback: &Knockback,
+    away: Vec2,
+    velocity: &mut Velocity,
+    entity_effects: &mut EntityEffects,
+) {
+    // A dash still running would multiply the knockback from the next step on.
+    entity_effects.drop_boosts();
+    velocity.set_current(away * knockback.speed + Vec2::new(0.0, knockback.lift));
+    if knockback.stun_time > 0.0 {
+        entity_effects.active.push(EntityEffectDescriptor::new_temporary(
+            HitStun,
+            Duration::from_secs_f32(knockback.stun_time),
+        ));
+    }
+}
@@ -1,14 +1,251 @@
+use crate::awards::{AbilityTimer, AwardTimer};
 use crate::effects::{ActiveEffects, EntityEffects, VisualEffects};
-use crate::enemies::Enemy;
-use crate::game::{Game, GameEntity, GameState};
-use crate::player::{self, Player};
-use crate::world::Velocity;
+use crate::enemies::{Enemy, Flying, SpawnTimer};
+use crate::game::{Game, GameEntity, GameStage, GameState, GameTime, Simulation};
+use crate::platforms::PlatformTimer;
+use crate::player::{self, Abilities, GroundPound, JumpControl, Player, PlayerEvent, PlayerInput};
+use crate::random::GameRng;
+use crate::systems::input::InputSystem;
+use crate::systems::physics::PhysicsSystem;
+use crate::systems::spawning::{SpawnSystem, SpawnTimers};
+use crate::systems::{events, input, physics, replay, spawning};
+use crate::tuning::{AbilityTuning, Tuning};
+use crate::world::{
+    AffectedByGravity, Collider, CollisionEvent, Deformation, Gravity, Interpolated, OnPlatform,
+    Velocity,
+};
 
+use bevy::ecs::schedule::ShouldRun;
 use bevy::prelude::*;
 use rand::Rng;
+use std::time::Duration;
 
-pub fn apply_effects(
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum GameplaySystem {
+    StartGame,
+}
+
+pub struct GameplayPlugin;
+
+impl Plugin for GameplayPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<Game>()
+            .init_resource::<GameTime>()
+            .init_resource::<GameRng>()
+            .init_resource::<PlayerInput>()
+            .init_resource::<Tuning>()
+            .add_event::<PlayerEvent>()
+            .add_event::<CollisionEvent>()
+            .add_startup_system(setup_game.system())
+            .add_system_to_stage(CoreStage::PreUpdate, accumulate_game_time.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Starting)
+                    .with_system(start_game.system().label(GameplaySystem::StartGame))
+                    .with_system(reset_player.system().label(GameplaySystem::StartGame))
+                    .with_system(despawn_game_entities.system().label(GameplaySystem::StartGame))
+                    .with_system(replay::start_recording.system().after(GameplaySystem::StartGame))
+                    .with_system(replay::restart_playback.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(replay::save_recording.system()),
+            )
+            .add_stage_after(
+                CoreStage::Update,
+                Simulation,
+                Schedule::default()
+                    .with_run_criteria(simulation_step.system())
+                    .with_stage(
+                        GameStage::AcceptInput,
+                        SystemStage::parallel()
+                            .with_system(
+                                replay::play_input
+                                    .system()
+                                    .label(InputSystem::PlayReplay)
+                                    .before(InputSystem::ApplyPlayerInput),
+                            )
+                            .with_system(
+                                replay::record_input
+                                    .system()
+                                    .after(InputSystem::PlayReplay)
+                                    .before(InputSystem::ApplyPlayerInput),
+                            )
+                            .with_system(
+                                input::apply_player_input
+                                    .system()
+                                    .label(InputSystem::ApplyPlayerInput),
+                            ),
+                    )
+                    .with_stage(
+                        GameStage::Effects,
+                        SystemStage::parallel()
+                            .with_system(apply_effects.system())
+                            .with_system(random_enemy_jump.system()),
+                    )
+                    .with_stage(
+                        GameStage::Movement,
+                        SystemStage::parallel()
+                            .with_system(
+                                physics::gravity
+                                    .system()
+                                    .label(PhysicsSystem::Gravity)
+                                    .before(PhysicsSystem::Movement),
+                            )
+                            .with_system(
+                                physics::movement.system().label(PhysicsSystem::Movement),
+                            )
+                            .with_system(
+                                physics::land_on_platforms
+                                    .system()
+                                    .label(PhysicsSystem::LandOnPlatforms)
+                                    .after(PhysicsSystem::Movement),
+                            )
+                            .with_system(
+                                physics::crouch
+                                    .system()
+                                    .after(PhysicsSystem::LandOnPlatforms),
+                            )
+                            .with_system(
+                                physics::land_ground_pound
+                                    .system()
+                                    .after(PhysicsSystem::LandOnPlatforms),
+                            ),
+                    )
+                    .with_stage(
+                        GameStage::Collisions,
+                        SystemStage::parallel()
+                            .with_system(
+                                physics::detect_collisions
+                                    .system()
+                                    .label(PhysicsSystem::Collisions),
+                            )
+                            .with_system(
+                                physics::shockwave_collisions
+                                    .system()
+                                    .label(PhysicsSystem::Shockwaves)
+                                    .after(PhysicsSystem::Collisions),
+                            )
+                            .with_system(
+                                physics::player_collisions
+                                    .system()
+                                    .label(PhysicsSystem::PlayerCollisions)
+                                    .after(PhysicsSystem::Shockwaves),
+                            )
+                            .with_system(
+                                physics::fall_out_of_world
+                                    .system()
+                                    .after(PhysicsSystem::PlayerCollisions),
+                            ),
+                    )
+                    .with_stage(
+                        GameStage::Events,
+                        SystemStage::parallel().with_system(events::player_events.system()),
+                    )
+                    .with_stage(
+                        GameStage::Cleanup,
+                        SystemStage::parallel()
+                            .with_system(track_time_survived.system())
+                            .with_system(physics::deform.system())
+                            .with_system(cleanup_effects.system())
+                            .with_system(spawning::drop_passed_obstacles.system())
+                            .with_system(
+                                spawning::spawn_obstacle.system().label(SpawnSystem::Obstacle),
+                            )
+                            .with_system(
+                                spawning::spawn_platform
+                                    .system()
+                                    .label(SpawnSystem::Platform)
+                                    .after(SpawnSystem::Obstacle),
+                            )
+                            .with_system(
+                                spawning::spawn_health
+                                    .system()
+                                    .label(SpawnSystem::Health)
+                                    .after(SpawnSystem::Platform),
+                            )
+                            .with_system(
+                                spawning::spawn_ability.system().after(SpawnSystem::Health),
+                            ),
+                    ),
+            );
+    }
+}
+
+fn setup_game(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let initial_position = Vec3::new(player::INITIAL_POSITION_X, player::INITIAL_POSITION_Y, 0.0);
+
+    commands.insert_resource(Gravity(tuning.world.gravity));
+    commands.insert_resource(AwardTimer::new(
+        tuning.awards.min_health_interval,
+        tuning.awards.max_health_interval,
+    ));
+    commands.insert_resource(AbilityTimer(AwardTimer::new(
+        tuning.abilities.min_pickup_interval,
+        tuning.abilities.max_pickup_interval,
+    )));
+    commands.insert_resource(SpawnTimer {
+        timer: Timer::from_seconds(tuning.enemies.initial_spawn_delay, true),
+    });
+    commands.insert_resource(PlatformTimer {
+        timer: Timer::from_seconds(tuning.platforms.initial_spawn_delay, true),
+    });
+
+    commands.spawn()
+        .insert(Player::new())
+        .insert(Collider::Player)
+        .insert(Player::new().hurtbox())
+        .insert(JumpControl::default())
+        .insert(GroundPound::default())
+        .insert(starting_abilities(&tuning.abilities))
+        .insert(GameEntity)
+        .insert(EntityEffects::default())
+        .insert(ActiveEffects::new())
+        .insert(VisualEffects::new())
+        .insert(Velocity::default())
+        .insert(AffectedByGravity::new(tuning.player.gravity_scale))
+        .insert(OnPlatform::default())
+        .insert(Deformation::default())
+        .insert(Interpolated::new(initial_position))
+        .insert_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(player::WIDTH, player::HEIGHT)),
+            material: materials.add(Color::BLACK.into()),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        });
+}
+
+fn starting_abilities(tuning: &AbilityTuning) -> Abilities {
+    let mut abilities = Abilities::default();
+    for &ability in &tuning.start_with {
+        abilities.unlock(ability, tuning.charges(ability));
+    }
+    abilities
+}
+
+fn accumulate_game_time(
     time: Res<Time>,
+    state: Res<State<GameState>>,
+    mut game_time: ResMut<GameTime>,
+) {
+    if *state == GameState::Running {
+        game_time.accumulate(time.delta());
+    }
+}
+
+fn simulation_step(state: Res<State<GameState>>, mut game_time: ResMut<GameTime>) -> ShouldRun {
+    if *state == GameState::Running && game_time.consume_step() {
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+pub fn apply_effects(
+    time: Res<GameTime>,
     mut query: Query<(Entity, &mut EntityEffects, &mut Velocity, &mut Transform)>,
 ) {
     for (entity, mut effects, mut velocity, mut transform) in query.iter_mut() {
@@ -24,8 +261,9 @@ pub fn apply_effects(
 }
 
 pub fn cleanup_effects(
-    time: Res<Time>,
+    time: Res<GameTime>,
     mut query: Query<(&mut ActiveEffects, &mut VisualEffects)>,
+    mut entity_effects_query: Query<&mut EntityEffects>,
 ) {
     for (mut effects, mut visual_effects) in query.iter_mut() {
         for effect in &mut effects.effects {
@@ -34,53 +272,116 @@ pub fn cleanup_effects(
         effects.effects.retain(|effect| effect.is_active());
         visual_effects.effects.retain(|effect| !effect.is_expired());
     }
+    // `apply_effects` has undone the expired ones by now.
+    for mut entity_effects in entity_effects_query.iter_mut() {
+        entity_effects.active.retain(|effect| !effect.is_expired());
+    }
 }
 
-pub fn random_enemy_jump(mut query: Query<&mut Velocity, With<Enemy>>) {
-    let mut rng = rand::thread_rng();
+pub fn track_time_survived(time: Res<GameTime>, mut game: ResMut<Game>) {
+    game.time_survived += time.delta_seconds();
+}
 
+pub fn random_enemy_jump(
+    tuning: Res<Tuning>,
+    mut rng: ResMut<GameRng>,
+    mut query: Query<&mut Velocity, (With<Enemy>, Without<Flying>)>,
+) {
     // TODO: Make it smarter.
     for mut velocity in query.iter_mut() {
         let mut v = velocity.current();
         if v.y == 0.0 && rng.gen_bool(0.01) {
-            velocity.set_vertical(crate::player::VELOCITY_ON_JUMP * 1.25);
+            velocity.set_vertical(tuning.player.jump_velocity * 1.25);
             break;
         }
     }
 }
 
 pub fn start_game(
-    mut commands: Commands,
     mut game: ResMut<Game>,
     mut state: ResMut<State<GameState>>,
-    mut player_query: Query<(
-        &mut Player,
-        &mut EntityEffects,
-        &mut ActiveEffects,
-        &mut VisualEffects,
-        &mut Velocity,
-        &mut Visible,
-        &mut Transform,
-    )>,
-    entities: Query<Entity, (With<GameEntity>, Without<Player>)>,
+    tuning: Res<Tuning>,
+    mut rng: ResMut<GameRng>,
+    mut timers: SpawnTimers,
+    mut player_input: ResMut<PlayerInput>,
+    mut time: ResMut<GameTime>,
 ) {
     game.score = 0.0;
+    game.time_survived = 0.0;
+
+    rng.reseed();
+    info!("Starting game with seed {}", rng.seed());
+
+    timers
+        .enemies
+        .timer
+        .set_duration(Duration::from_secs_f32(tuning.enemies.initial_spawn_delay));
+    timers.enemies.timer.reset();
+    timers
+        .platforms
+        .timer
+        .set_duration(Duration::from_secs_f32(tuning.platforms.initial_spawn_delay));
+    timers.platforms.timer.reset();
+    timers.awards.restart(&mut *rng);
+    timers.abilities.0.restart(&mut *rng);
+    *player_input = PlayerInput::default();
+    time.restart();
 
+    state.set(GameState::Running).unwrap();
+}
+
+fn despawn_game_entities(
+    mut commands: Commands,
+    entities: Query<Entity, (With<GameEntity>, Without<Player>)>,
+) {
     for entity in entities.iter() {
         commands.entity(entity).despawn();
     }
+}
+
+/// Everything about the player a new run starts over.
+type PlayerRun = (
+    &'static mut Player,
+    &'static mut JumpControl,
+    &'static mut GroundPound,
+    &'static mut Abilities,
+    &'static mut EntityEffects,
+    &'static mut ActiveEffects,
+    &'static mut VisualEffects,
+    &'static mut Velocity,
+    &'static mut Visible,
+    &'static mut Transform,
+    &'static mut Interpolated,
+    &'static mut OnPlatform,
+    &'static mut Deformation,
+    &'static mut AffectedByGravity,
+);
 
+fn reset_player(tuning: Res<Tuning>, mut player_query: Query<PlayerRun>) {
     for (
         mut player,
+        mut jump,
+        mut ground_pound,
+        mut abilities,
         mut entity_effects,
         mut active_effects,
         mut visual_effects,
         mut velocity,
         mut visibility,
         mut transform,
+        mut interpolated,
+        mut on_platform,
+        mut deformation,
+        mut affected_by_gravity,
     ) in player_query.iter_mut()
     {
         player.health = player.max_health;
+        player.stomp_combo = 0;
+        player.crouching = false;
+        player.slide_time_left = 0.0;
+        *jump = JumpControl::default();
+        *ground_pound = GroundPound::default();
+        *abilities = starting_abilities(&tuning.abilities);
 
         entity_effects.active.clear();
         active_effects.effects.clear();
@@ -89,7 +390,9 @@ pub fn start_game(
         visibility.is_visible = true;
         transform.translation.x = player::INITIAL_POSITION_X;
         transform.translation.y = player::INITIAL_POSITION_Y;
+        *interpolated = Interpolated::new(transform.translation);
+        on_platform.0 = None;
+        deformation.reset();
+        *affected_by_gravity = AffectedByGravity::new(tuning.player.gravity_scale);
     }
-
-    state.set(GameState::Running).unwrap();
 }
@@ -0,0 +1,102 @@
+use crate::game;
+use crate::player::Player;
+use crate::systems::spawning::SpawnTimers;
+use crate::tuning::Tuning;
+use crate::world::{AffectedByGravity, Gravity};
+use bevy::prelude::*;
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+const POLL_INTERVAL: f32 = 0.5;
+
+/// The tuning file the game was started with. Changes to it are applied while the game runs.
+pub struct TuningWatcher {
+    path: PathBuf,
+    last_modified: Option<SystemTime>,
+    timer: Timer,
+    pub error: Option<String>,
+}
+
+impl TuningWatcher {
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            last_modified: last_modified(&path),
+            path,
+            timer: Timer::from_seconds(POLL_INTERVAL, true),
+            error: None,
+        }
+    }
+}
+
+pub struct HotReloadPlugin;
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(reload_tuning.system());
+    }
+}
+
+fn reload_tuning(
+    time: Res<Time>,
+    watcher: Option<ResMut<TuningWatcher>>,
+    mut tuning: ResMut<Tuning>,
+    mut gravity: ResMut<Gravity>,
+    mut timers: SpawnTimers,
+    mut player_gravity: Query<&mut AffectedByGravity, With<Player>>,
+) {
+    let mut watcher = match watcher {
+        Some(watcher) => watcher,
+        None => return,
+    };
+
+    if !watcher.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let modified = last_modified(&watcher.path);
+    if modified == watcher.last_modified {
+        return;
+    }
+    watcher.last_modified = modified;
+
+    match Tuning::load(&watcher.path) {
+        Ok(new_tuning) => {
+            gravity.0 = new_tuning.world.gravity;
+            for mut affected_by_gravity in player_gravity.iter_mut() {
+                affected_by_gravity.scale = new_tuning.player.gravity_scale;
+            }
+            game::clamp_interval(
+                &mut timers.enemies.timer,
+                new_tuning.enemies.min_spawn_interval,
+                new_tuning.enemies.max_spawn_interval,
+            );
+            game::clamp_interval(
+                &mut timers.platforms.timer,
+                new_tuning.platforms.min_spawn_interval,
+                new_tuning.platforms.max_spawn_interval,
+            );
+            timers.awards.set_range(
+                new_tuning.awards.min_health_interval,
+                new_tuning.awards.max_health_interval,
+            );
+            timers.abilities.0.set_range(
+                new_tuning.abilities.min_pickup_interval,
+                new_tuning.abilities.max_pickup_interval,
+            );
+            *tuning = new_tuning;
+
+            info!("Reloaded tuning from {}", watcher.path.display());
+            watcher.error = None;
+        }
+        Err(err) => {
+            warn!("{}", err);
+            watcher.error = Some(err.to_string());
+        }
+    }
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -1,6 +1,9 @@
 use crate::effects::{ActiveEffects, EffectLength};
 use crate::game::{Game, GameState};
-use crate::player::Player;
+use crate::player::{Abilities, Ability, Player, PlayerEvent};
+use crate::records::Leaderboard;
+use crate::systems::hot_reload::TuningWatcher;
+use crate::systems::records::NameEntry;
 use bevy::prelude::*;
 
 const PLAYER_STATUS_BAR_TOP_MARGIN: f32 = 16.0;
@@ -12,6 +15,9 @@ const HEALTH_BAR_HEIGHT: f32 = 64.0;
 const HEALTH_INDICATOR_WIDTH: f32 = 64.0;
 const HEALTH_INDICATOR_HEIGHT: f32 = 64.0;
 
+/// Seconds the enemies destroyed by a ground pound stay on the scoreboard.
+const GROUND_POUND_LABEL_TIME: f64 = 1.5;
+
 struct Scoreboard;
 
 struct GameStateLabel;
@@ -22,6 +28,10 @@ struct HealthIndicator {
 
 struct ActiveEffectsBar;
 
+struct TuningErrorLabel;
+
+struct LeaderboardTable;
+
 pub struct HudPlugin;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, StageLabel)]
@@ -33,10 +43,14 @@ impl Plugin for HudPlugin {
             .add_startup_system_to_stage(Stage, setup_scoreboard.system())
             .add_startup_system_to_stage(Stage, setup_health_bar.system())
             .add_startup_system_to_stage(Stage, setup_game_status.system())
+            .add_startup_system_to_stage(Stage, setup_tuning_error.system())
+            .add_startup_system_to_stage(Stage, setup_leaderboard.system())
             .add_system(update_scoreboard.system())
             .add_system(update_health_bar.system())
             .add_system(update_active_effects.system())
-            .add_system(update_game_state_screen.system());
+            .add_system(update_game_state_screen.system())
+            .add_system(update_tuning_error.system())
+            .add_system(update_leaderboard.system());
     }
 }
 
@@ -65,9 +79,50 @@ fn setup_scoreboard(mut commands: Commands, asset_server: ResMut<AssetServer>) {
         .insert(Scoreboard);
 }
 
-fn update_scoreboard(game: Res<Game>, mut query: Query<&mut Text, With<Scoreboard>>) {
+fn update_scoreboard(
+    game: Res<Game>,
+    time: Res<Time>,
+    mut events: EventReader<PlayerEvent>,
+    mut last_ground_pound: Local<Option<(u32, f64)>>,
+    player_query: Query<(&Player, &Abilities)>,
+    mut query: Query<&mut Text, With<Scoreboard>>,
+) {
+    let now = time.seconds_since_startup();
+    for event in events.iter() {
+        if let PlayerEvent::GroundPound { destroyed } = event {
+            if *destroyed > 0 {
+                *last_ground_pound = Some((*destroyed, now));
+            }
+        }
+    }
+
     for mut text in query.iter_mut() {
         text.sections[0].value = format!("Score: {}. Best score: {}", game.score, game.best_score);
+        if let Some((destroyed, at)) = *last_ground_pound {
+            if now - at < GROUND_POUND_LABEL_TIME {
+                text.sections[0]
+                    .value
+                    .push_str(&format!("\nShockwave x{}", destroyed));
+            }
+        }
+        for (player, abilities) in player_query.iter() {
+            if player.stomp_combo > 1 {
+                text.sections[0]
+                    .value
+                    .push_str(&format!("\nCombo x{}", player.stomp_combo));
+            }
+            for &ability in Ability::ALL.iter() {
+                let charges = abilities.get(ability);
+                if charges.unlocked {
+                    text.sections[0].value.push_str(&format!(
+                        "\n{}: {}/{}",
+                        ability.name(),
+                        charges.left,
+                        charges.max
+                    ));
+                }
+            }
+        }
     }
 }
 
@@ -222,6 +277,7 @@ fn update_active_effects(
 fn update_game_state_screen(
     state: Res<State<GameState>>,
     game: Res<Game>,
+    name_entry: Option<Res<NameEntry>>,
     mut query: Query<(&mut Text, &mut Visible), With<GameStateLabel>>,
 ) {
     for (mut text, mut visibility) in query.iter_mut() {
@@ -240,8 +296,15 @@ fn update_game_state_screen(
             }
             GameState::GameOver => {
                 visibility.is_visible = true;
-                text.sections[0].value =
-                    format!("Game over!\nYour score: {}\nPress R to restart", game.score);
+                text.sections[0].value = match name_entry.as_ref().and_then(|e| e.name.as_ref()) {
+                    Some(name) => format!(
+                        "New high score: {}!\nName: {}_\nPress Enter to save",
+                        game.score, name
+                    ),
+                    None => {
+                        format!("Game over!\nYour score: {}\nPress R to restart", game.score)
+                    }
+                };
             }
         }
     }
@@ -266,3 +329,103 @@ fn setup_game_status(mut commands: Commands, asset_server: ResMut<AssetServer>)
     })
         .insert(GameStateLabel);
 }
+
+fn setup_tuning_error(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+    commands.spawn_bundle(TextBundle {
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                color: Color::RED,
+                font_size: 20.0,
+            },
+            Default::default(),
+        ),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                bottom: Val::Px(5.0),
+                left: Val::Px(5.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+        .insert(TuningErrorLabel);
+}
+
+fn update_tuning_error(
+    watcher: Option<Res<TuningWatcher>>,
+    mut query: Query<&mut Text, With<TuningErrorLabel>>,
+) {
+    let error = match watcher.as_ref().and_then(|watcher| watcher.error.as_ref()) {
+        Some(error) => format!("Tuning not applied: {}", error),
+        None => String::new(),
+    };
+
+    for mut text in query.iter_mut() {
+        if text.sections[0].value != error {
+            text.sections[0].value = error.clone();
+        }
+    }
+}
+
+fn setup_leaderboard(mut commands: Commands, asset_server: ResMut<AssetServer>) {
+    commands.spawn_bundle(TextBundle {
+        text: Text::with_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                color: Color::rgb(0.5, 0.5, 0.5),
+                font_size: 24.0,
+            },
+            Default::default(),
+        ),
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: Rect {
+                top: Val::Px(64.0),
+                right: Val::Px(16.0),
+                ..Default::default()
+            },
+            ..Default::default()
+        },
+        ..Default::default()
+    })
+        .insert(LeaderboardTable);
+}
+
+fn update_leaderboard(
+    state: Res<State<GameState>>,
+    leaderboard: Option<Res<Leaderboard>>,
+    mut query: Query<(&mut Text, &mut Visible), With<LeaderboardTable>>,
+) {
+    let leaderboard = match leaderboard {
+        Some(leaderboard) => leaderboard,
+        None => return,
+    };
+
+    for (mut text, mut visibility) in query.iter_mut() {
+        visibility.is_visible =
+            matches!(*state.current(), GameState::StartMenu | GameState::GameOver);
+        if !visibility.is_visible {
+            continue;
+        }
+
+        let mut table = String::from("Leaderboard\n");
+        for (place, entry) in leaderboard.entries.iter().enumerate() {
+            let survived = entry.time_survived as u32;
+            table.push_str(&format!(
+                "{:>2}. {:<12} {:>6} {:>2}:{:02} {}\n",
+                place + 1,
+                entry.name,
+                entry.score,
+                survived / 60,
+                survived % 60,
+                entry.date,
+            ));
+        }
+        text.sections[0].value = table;
+    }
+}
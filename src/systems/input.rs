@@ -1,6 +1,8 @@
 use crate::effects::{EntityEffectDescriptor, EntityEffects, SpeedBoost};
-use crate::game::GameState;
-use crate::player::{self, Player, PlayerMovementState};
+use crate::game::{GameState, GameTime};
+use crate::player::{Abilities, GroundPound, JumpControl, Player, PlayerInput, PlayerMovementState};
+use crate::systems::records::NameEntry;
+use crate::tuning::Tuning;
 use crate::world::Velocity;
 use bevy::prelude::*;
 use std::collections::HashMap;
@@ -15,6 +17,12 @@ struct InputTracker {
     last_pressed: Option<KeyCode>,
 }
 
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum InputSystem {
+    PlayReplay,
+    ApplyPlayerInput,
+}
+
 pub struct InputPlugin;
 
 impl Plugin for InputPlugin {
@@ -51,7 +59,7 @@ fn running_game_input(
     mut input: ResMut<Input<KeyCode>>,
     mut state: ResMut<State<GameState>>,
     mut input_tracker: ResMut<InputTracker>,
-    mut query: Query<(&mut Player, &mut Velocity, &mut EntityEffects)>,
+    mut player_input: ResMut<PlayerInput>,
     #[cfg(feature = "debug")] mut debug_query: Query<(Entity, &Children), With<DebugBlock>>,
     #[cfg(feature = "debug")] mut visibility_query: Query<(&mut Visible), With<DebugBlock>>,
 ) {
@@ -81,47 +89,253 @@ fn running_game_input(
         state.set(GameState::Paused).unwrap();
     }
 
-    for (mut player, mut velocity, mut effects) in query.iter_mut() {
-        match player.movement_state {
-            PlayerMovementState::Staying | PlayerMovementState::Running => {
-                if input.pressed(KeyCode::Up) || input.pressed(KeyCode::Space) {
-                    player.movement_state = PlayerMovementState::Jumping;
-                    velocity.set_vertical(player::VELOCITY_ON_JUMP);
-                }
+    player_input.jump = input.pressed(KeyCode::Up) || input.pressed(KeyCode::Space);
+    player_input.left = input.pressed(KeyCode::Left);
+    player_input.right = input.pressed(KeyCode::Right);
+    player_input.down = input.pressed(KeyCode::Down);
 
-                if input.pressed(KeyCode::Left) {
-                    velocity.set_horizontal(-player::MOVEMENT_VELOCITY);
+    // Only tapping a direction twice dashes, a double jump or crouching twice doesn't.
+    let direction_pressed = input
+        .get_just_pressed()
+        .filter(|key| matches!(key, KeyCode::Left | KeyCode::Right))
+        .last();
+    if let Some(just_pressed) = direction_pressed {
+        let now = time.seconds_since_startup();
+        if input_tracker.last_pressed.is_some()
+            && *just_pressed == input_tracker.last_pressed.unwrap()
+        {
+            if let Some(last_press_time) = input_tracker.last_press_time.get(&just_pressed) {
+                if now - last_press_time < 0.5 {
+                    player_input.dash = true;
                 }
+            }
+        }
 
-                if input.pressed(KeyCode::Right) {
-                    velocity.set_horizontal(player::MOVEMENT_VELOCITY);
-                }
+        input_tracker.last_press_time.insert(*just_pressed, now);
+        input_tracker.last_pressed = Some(*just_pressed);
+    }
+}
 
-                if let Some(just_pressed) = input.get_just_pressed().last() {
-                    let now = time.seconds_since_startup();
-                    if input_tracker.last_pressed.is_some()
-                        && *just_pressed == input_tracker.last_pressed.unwrap()
-                    {
-                        if let Some(last_press_time) =
-                            input_tracker.last_press_time.get(&just_pressed)
-                        {
-                            if now - last_press_time < 0.5 {
-                                let boost = SpeedBoost::horizontal(3.0);
-                                let duration = Duration::from_millis(150);
-                                effects
-                                    .active
-                                    .push(EntityEffectDescriptor::new_temporary(boost, duration))
-                            }
-                        }
-                    }
+pub fn apply_player_input(
+    time: Res<GameTime>,
+    tuning: Res<Tuning>,
+    mut player_input: ResMut<PlayerInput>,
+    mut query: Query<(
+        &mut Player,
+        &mut JumpControl,
+        &mut GroundPound,
+        &mut Abilities,
+        &mut Velocity,
+        &mut EntityEffects,
+    )>,
+) {
+    for (mut player, mut jump, mut ground_pound, mut abilities, mut velocity, mut effects) in
+        query.iter_mut()
+    {
+        let on_ground = player.is_on_ground();
+        if on_ground {
+            abilities.refill();
+        }
 
-                    input_tracker.last_press_time.insert(*just_pressed, now);
-                    input_tracker.last_pressed = Some(*just_pressed);
-                }
+        ground_pound.cooldown_left = (ground_pound.cooldown_left - time.delta_seconds()).max(0.0);
+        let down_pressed = player_input.down && !ground_pound.down_held;
+        ground_pound.down_held = player_input.down;
+        if ground_pound.slamming && velocity.vertical() > 0.0 {
+            // A stomp or a knockback sent the player back up, the slam is over without a shockwave.
+            ground_pound.slamming = false;
+        }
+
+        if effects.is_stunned() || ground_pound.slamming {
+            // A jump held through the stun or slam shouldn't fire or get cut short after it, and
+            // a ledge left or a jump pressed before it doesn't give a jump after it either.
+            jump.held = player_input.jump;
+            jump.rising = false;
+            jump.coyote_time_left = 0.0;
+            jump.buffer_time_left = 0.0;
+            continue;
+        }
+
+        if down_pressed && !on_ground && ground_pound.cooldown_left == 0.0 {
+            ground_pound.slamming = true;
+            velocity.set_current(Vec2::new(0.0, -tuning.ground_pound.velocity));
+            jump.held = player_input.jump;
+            jump.rising = false;
+            jump.coyote_time_left = 0.0;
+            jump.buffer_time_left = 0.0;
+            continue;
+        }
+
+        crouch_and_slide(&time, &tuning, &player_input, &mut player, &mut velocity);
+
+        apply_jump_input(
+            &time,
+            &tuning,
+            &player_input,
+            &mut player,
+            &mut jump,
+            &mut abilities,
+            &mut velocity,
+        );
+
+        if player.slide_time_left == 0.0 {
+            let mut steering = *player_input;
+            if player.crouching {
+                steering.left = false;
+                steering.right = false;
             }
-            _ => {}
+            accelerate(&time, &tuning, &steering, on_ground, &mut velocity);
+        }
+
+        if player_input.dash {
+            if on_ground {
+                let boost = SpeedBoost::horizontal(3.0);
+                let duration = Duration::from_millis(150);
+                effects
+                    .active
+                    .push(EntityEffectDescriptor::new_temporary(boost, duration))
+            } else {
+                air_dash(&tuning, &player_input, &mut abilities, &mut velocity, &mut effects);
+            }
+        }
+    }
+
+    player_input.dash = false;
+}
+
+/// Crouches while down is held on the ground. Crouching while running starts a slide that keeps
+/// its speed for a while, crouching in place or after the slide stops the player.
+fn crouch_and_slide(
+    time: &GameTime,
+    tuning: &Tuning,
+    player_input: &PlayerInput,
+    player: &mut Player,
+    velocity: &mut Velocity,
+) {
+    let on_ground = player.is_on_ground();
+    let crouching = player_input.down && on_ground;
+
+    if crouching && !player.crouching && player.movement_state == PlayerMovementState::Running {
+        player.slide_time_left = tuning.player.slide_time;
+        velocity.set_horizontal(velocity.horizontal().signum() * tuning.player.slide_velocity);
+    } else if on_ground {
+        player.slide_time_left = (player.slide_time_left - time.delta_seconds()).max(0.0);
+    } else {
+        player.slide_time_left = 0.0;
+    }
+
+    player.crouching = crouching;
+}
+
+/// Speeds up towards the held direction and slows down when nothing or both are held. Turning
+/// around brakes and accelerates at once.
+fn accelerate(
+    time: &GameTime,
+    tuning: &Tuning,
+    player_input: &PlayerInput,
+    on_ground: bool,
+    velocity: &mut Velocity,
+) {
+    let (acceleration, deceleration) = if on_ground {
+        (tuning.player.ground_acceleration, tuning.player.ground_deceleration)
+    } else {
+        (tuning.player.air_acceleration, tuning.player.air_deceleration)
+    };
+
+    let direction = match (player_input.left, player_input.right) {
+        (true, false) => -1.0,
+        (false, true) => 1.0,
+        _ => 0.0,
+    };
+    let target = direction * tuning.player.movement_velocity;
+
+    let rate = if direction == 0.0 {
+        deceleration
+    } else if velocity.horizontal() * direction < 0.0 {
+        acceleration + deceleration
+    } else {
+        acceleration
+    };
+
+    velocity.approach_horizontal(target, rate * time.delta_seconds());
+}
+
+fn apply_jump_input(
+    time: &GameTime,
+    tuning: &Tuning,
+    player_input: &PlayerInput,
+    player: &mut Player,
+    jump: &mut JumpControl,
+    abilities: &mut Abilities,
+    velocity: &mut Velocity,
+) {
+    let pressed = player_input.jump && !jump.held;
+    jump.held = player_input.jump;
+
+    if pressed {
+        jump.buffer_time_left = tuning.player.jump_buffer_time;
+    }
+
+    let on_ground = player.is_on_ground();
+    if on_ground {
+        jump.coyote_time_left = tuning.player.coyote_time;
+    }
+
+    if (pressed || jump.buffer_time_left > 0.0) && (on_ground || jump.coyote_time_left > 0.0) {
+        player.movement_state = PlayerMovementState::Jumping;
+        velocity.set_vertical(tuning.player.jump_velocity);
+        jump.rising = true;
+        jump.buffer_time_left = 0.0;
+        jump.coyote_time_left = 0.0;
+    } else if pressed && abilities.double_jump.use_charge() {
+        player.movement_state = PlayerMovementState::Jumping;
+        velocity.set_vertical(tuning.abilities.double_jump_velocity);
+        jump.rising = true;
+        jump.buffer_time_left = 0.0;
+    } else {
+        jump.buffer_time_left = (jump.buffer_time_left - time.delta_seconds()).max(0.0);
+        jump.coyote_time_left = (jump.coyote_time_left - time.delta_seconds()).max(0.0);
+    }
+
+    if jump.rising && velocity.vertical() <= 0.0 {
+        jump.rising = false;
+    } else if jump.rising && !player_input.jump {
+        velocity.set_vertical(velocity.vertical() * tuning.player.jump_cut);
+        jump.rising = false;
+    }
+}
+
+/// Dashes towards the held direction, or the one the player is already moving in.
+fn air_dash(
+    tuning: &Tuning,
+    player_input: &PlayerInput,
+    abilities: &mut Abilities,
+    velocity: &mut Velocity,
+    effects: &mut EntityEffects,
+) {
+    let direction = if player_input.left != player_input.right {
+        if player_input.left {
+            -1.0
+        } else {
+            1.0
         }
+    } else if velocity.horizontal() != 0.0 {
+        velocity.horizontal().signum()
+    } else {
+        return;
+    };
+
+    if !abilities.air_dash.use_charge() {
+        return;
     }
+
+    velocity.set_horizontal(direction * tuning.player.movement_velocity);
+    velocity.drop_vertical();
+
+    let boost = SpeedBoost::horizontal(tuning.abilities.air_dash_boost);
+    let duration = Duration::from_secs_f32(tuning.abilities.air_dash_duration);
+    effects
+        .active
+        .push(EntityEffectDescriptor::new_temporary(boost, duration));
 }
 
 fn paused_game_input(mut input: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
@@ -135,7 +349,15 @@ fn paused_game_input(mut input: ResMut<Input<KeyCode>>, mut state: ResMut<State<
     }
 }
 
-fn game_over_menu_input(mut input: ResMut<Input<KeyCode>>, mut state: ResMut<State<GameState>>) {
+fn game_over_menu_input(
+    mut input: ResMut<Input<KeyCode>>,
+    mut state: ResMut<State<GameState>>,
+    name_entry: Option<Res<NameEntry>>,
+) {
+    if name_entry.map_or(false, |entry| entry.name.is_some()) {
+        return;
+    }
+
     if input.just_pressed(KeyCode::R) {
         input.reset(KeyCode::R);
         state.set(GameState::Starting).unwrap();
@@ -0,0 +1,35 @@
+use crate::game::GameTime;
+use crate::world::{Deformation, Interpolated};
+use bevy::prelude::*;
+
+pub fn interpolate_translations(
+    time: Res<GameTime>,
+    mut query: Query<(
+        &mut Interpolated,
+        &mut Transform,
+        Option<&Sprite>,
+        Option<&Deformation>,
+    )>,
+) {
+    let alpha = time.overstep();
+    for (mut interpolated, mut transform, sprite, deformation) in query.iter_mut() {
+        interpolated.current = transform.translation;
+        transform.translation = interpolated.previous.lerp(interpolated.current, alpha);
+
+        if let (Some(sprite), Some(deformation)) = (sprite, deformation) {
+            transform.translation += deformation.offset();
+            transform.scale = deformation.scale(sprite.size);
+        }
+    }
+}
+
+pub fn restore_translations(
+    mut query: Query<(&Interpolated, &mut Transform, Option<&Deformation>)>,
+) {
+    for (interpolated, mut transform, deformation) in query.iter_mut() {
+        transform.translation = interpolated.current;
+        if deformation.is_some() {
+            transform.scale = Vec3::ONE;
+        }
+    }
+}
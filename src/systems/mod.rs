@@ -1,14 +1,21 @@
 pub mod debug;
 pub mod events;
 pub mod gameplay;
+pub mod hot_reload;
 pub mod hud;
 pub mod input;
+pub mod interpolation;
 pub mod physics;
+pub mod records;
+pub mod replay;
 pub mod spawning;
 pub mod visual_effects;
 
 pub mod plugins {
     pub use super::debug::DebugPlugin;
+    pub use super::gameplay::GameplayPlugin;
+    pub use super::hot_reload::HotReloadPlugin;
     pub use super::hud::HudPlugin;
     pub use super::input::InputPlugin;
+    pub use super::records::RecordsPlugin;
 }
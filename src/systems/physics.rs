@@ -1,84 +1,598 @@
-use crate::player::{self, Player, PlayerEvent};
-use crate::world::{Collider, Gravity, Velocity};
+use crate::broadphase::UniformGrid;
+use crate::effects::{EntityEffectDescriptor, EntityEffects, SpeedBoost};
+use crate::enemies::{Enemy, Flying};
+use crate::game::{GameEntity, GameTime};
+use crate::pits::Pit;
+use crate::platforms::Platform;
+use crate::player::{self, GroundPound, Player, PlayerEvent, PlayerMovementState, Shockwave};
+use crate::shapes::{self, Shape};
+use crate::tuning::Tuning;
+use crate::world::{
+    self, AffectedByGravity, Collider, CollisionEvent, CollisionPhase, Deformation, Gravity,
+    GravityZone, Hitbox, Hurtbox, Interpolated, Knockback, OnPlatform, Velocity,
+};
 use bevy::prelude::*;
-use bevy::sprite::collide_aabb;
+
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum PhysicsSystem {
+    Gravity,
+    Movement,
+    LandOnPlatforms,
+    Collisions,
+    Shockwaves,
+    PlayerCollisions,
+}
 
 pub fn gravity(
-    time: Res<Time>,
+    time: Res<GameTime>,
     gravity: Res<Gravity>,
-    mut query: Query<(&mut Velocity, &Sprite, &Transform)>,
+    pits: Query<(&Sprite, &Transform), With<Pit>>,
+    zones: Query<(&GravityZone, &Sprite, &Transform)>,
+    mut query: Query<(
+        &mut Velocity,
+        &Sprite,
+        &Transform,
+        &AffectedByGravity,
+        Option<&OnPlatform>,
+    )>,
 ) {
-    for (mut velocity, sprite, transform) in query.iter_mut() {
-        if transform.translation.y > sprite.size.y / 2.0 {
-            velocity.add_vertical(-gravity.0 * time.delta_seconds());
+    let pits: Vec<(f32, f32)> = pits
+        .iter()
+        .map(|(sprite, transform)| pit_span(sprite, transform.translation.x))
+        .collect();
+
+    for (mut velocity, sprite, transform, affected_by_gravity, on_platform) in query.iter_mut() {
+        let position = transform.translation;
+        let zone_scale = zones
+            .iter()
+            .find(|(_, zone_sprite, zone_transform)| {
+                let offset = (position - zone_transform.translation).truncate().abs();
+                offset.x < zone_sprite.size.x / 2.0 && offset.y < zone_sprite.size.y / 2.0
+            })
+            .map_or(1.0, |(zone, _, _)| zone.scale);
+        let pull = gravity.0 * affected_by_gravity.scale * zone_scale;
+
+        // Whatever holds the entity up doesn't stop it from falling upwards.
+        if pull > 0.0 {
+            if on_platform.map_or(false, |on_platform| on_platform.0.is_some()) {
+                continue;
+            }
+
+            let half_height = sprite.size.y / 2.0;
+            let in_air = position.y > half_height;
+            let in_pit = position.y < half_height
+                || over_pit(&pits, position.x, sprite.size.x / 2.0);
+            if !in_air && !in_pit {
+                continue;
+            }
         }
+
+        velocity.add_vertical(-pull * time.delta_seconds());
     }
 }
 
-pub fn movement(
-    time: Res<Time>,
-    game_window: Res<WindowDescriptor>,
-    mut player_entity_query: Query<(Entity, &mut Player)>,
-    mut query: Query<(Entity, &mut Velocity, &Sprite, &mut Transform)>,
-) {
-    let window_half_x = game_window.width as f32 / 2.0;
+type Moving = (
+    &'static mut Velocity,
+    &'static Sprite,
+    &'static mut Transform,
+    Option<&'static mut Interpolated>,
+    Option<&'static Pit>,
+    Option<&'static AffectedByGravity>,
+    Option<&'static mut Player>,
+);
+
+pub fn movement(time: Res<GameTime>, mut query: Query<Moving>) {
+    let window_half_x = world::SCREEN_WIDTH / 2.0;
     let window_left_border = -window_half_x;
     let window_right_border = window_half_x;
+    let window_top_border = world::SCREEN_HEIGHT / 2.0;
+
+    // Where the pits will be at the end of this step.
+    let mut pits = Vec::new();
+    for (velocity, sprite, transform, _, pit, _, _) in query.iter_mut() {
+        if pit.is_some() {
+            let x = transform.translation.x + velocity.horizontal() * time.delta_seconds();
+            pits.push(pit_span(sprite, x));
+        }
+    }
+
+    for (mut velocity, sprite, mut transform, interpolated, pit, affected_by_gravity, player) in
+        query.iter_mut()
+    {
+        let start = transform.translation;
+        if let Some(mut interpolated) = interpolated {
+            interpolated.previous = start;
+        }
 
-    for (entity, mut velocity, sprite, mut transform) in query.iter_mut() {
         {
             let v = velocity.current();
             transform.translation.x += v.x * time.delta_seconds();
             transform.translation.y += v.y * time.delta_seconds();
         }
 
-        if transform.translation.y <= sprite.size.y / 2.0 {
+        if pit.is_some() {
+            continue;
+        }
+
+        let half_width = sprite.size.x / 2.0;
+        let half_height = sprite.size.y / 2.0;
+        let mut on_ground = transform.translation.y <= half_height;
+        if affected_by_gravity.is_some() {
+            if start.y < half_height {
+                // Already below the ground, the pit's walls keep it inside.
+                on_ground = false;
+                if let Some(&(left, right)) =
+                    pits.iter().find(|(left, right)| *left <= start.x && start.x <= *right)
+                {
+                    let x = transform.translation.x;
+                    transform.translation.x = x.max(left + half_width).min(right - half_width);
+                    if transform.translation.x != x {
+                        velocity.drop_horizontal();
+                    }
+                }
+            } else if over_pit(&pits, transform.translation.x, half_width) {
+                on_ground = false;
+            }
+        }
+
+        if on_ground {
             velocity.drop_vertical();
-            transform.translation.y = sprite.size.y / 2.0;
+            transform.translation.y = half_height;
+        }
+
+        if let Some(mut player) = player {
+            if transform.translation.x - half_width <= window_left_border {
+                transform.translation.x = window_left_border + half_width;
+                velocity.drop_horizontal();
+            } else if transform.translation.x + half_width >= window_right_border {
+                transform.translation.x = window_right_border - half_width;
+                velocity.drop_horizontal();
+            }
+            if transform.translation.y + half_height >= window_top_border {
+                transform.translation.y = window_top_border - half_height;
+                velocity.drop_vertical();
+            }
+            if on_ground {
+                player.stomp_combo = 0;
+            }
+            player::update_movement_state(&mut player, &velocity);
+        }
+    }
+}
+
+/// Squashes sprites that stopped falling and stretches those launched upwards, then springs
+/// them back to rest.
+pub fn deform(time: Res<GameTime>, mut query: Query<(&Velocity, &mut Deformation)>) {
+    for (velocity, mut deformation) in query.iter_mut() {
+        let v = velocity.current();
+        let change = v.y - deformation.last_velocity.y;
+        deformation.last_velocity = v;
+
+        if change > world::DEFORMATION_THRESHOLD {
+            let amount = (change * world::DEFORMATION_PER_VELOCITY).min(world::MAX_DEFORMATION);
+            if v.y > 0.0 {
+                deformation.stretch(amount);
+            } else {
+                deformation.squash(amount);
+            }
+        }
+
+        deformation.spring_back(
+            world::DEFORMATION_STIFFNESS,
+            world::DEFORMATION_DAMPING,
+            time.delta_seconds(),
+        );
+    }
+}
+
+/// How far below a platform's top an entity may end up and still land on it.
+const LANDING_TOLERANCE: f32 = 0.5;
+
+type Rider = (
+    &'static mut OnPlatform,
+    &'static mut Velocity,
+    &'static Sprite,
+    &'static mut Transform,
+    &'static Interpolated,
+    Option<&'static mut Player>,
+);
+
+/// Lands entities falling onto a platform from above and carries them along while they stand on
+/// it. Platforms don't stop anything moving up or sideways.
+pub fn land_on_platforms(
+    mut riders: Query<Rider, Without<Platform>>,
+    platforms: Query<(Entity, &Sprite, &Transform, &Interpolated), With<Platform>>,
+) {
+    for (mut on_platform, mut velocity, sprite, mut transform, interpolated, mut player) in
+        riders.iter_mut()
+    {
+        let standing_on = on_platform.0.take();
+        if velocity.vertical() > 0.0 {
+            continue;
         }
 
-        for (player_entity, mut player) in player_entity_query.iter_mut() {
-            if entity == player_entity {
-                let player_sprite_half_x = sprite.size.x / 2.0;
+        let half_height = sprite.size.y / 2.0;
+        let bottom_start = interpolated.previous.y - half_height;
+        let bottom_end = transform.translation.y - half_height;
+
+        for (platform_entity, platform_sprite, platform_transform, platform_interpolated) in
+            platforms.iter()
+        {
+            let platform_half_height = platform_sprite.size.y / 2.0;
+            let top_start = platform_interpolated.previous.y + platform_half_height;
+            let top_end = platform_transform.translation.y + platform_half_height;
+            let overlaps_x = (transform.translation.x - platform_transform.translation.x).abs()
+                < (sprite.size.x + platform_sprite.size.x) / 2.0;
+
+            if overlaps_x
+                && bottom_start >= top_start - LANDING_TOLERANCE
+                && bottom_end <= top_end + LANDING_TOLERANCE
+            {
+                if standing_on == Some(platform_entity) {
+                    transform.translation.x +=
+                        platform_transform.translation.x - platform_interpolated.previous.x;
+                    if player.is_some() {
+                        // Riding doesn't carry the player past the window borders `movement` keeps
+                        // them in.
+                        let window_half_x = world::SCREEN_WIDTH / 2.0;
+                        let half_width = sprite.size.x / 2.0;
+                        transform.translation.x = transform
+                            .translation
+                            .x
+                            .max(-window_half_x + half_width)
+                            .min(window_half_x - half_width);
+                    }
+                }
+                transform.translation.y = top_end + half_height;
+                velocity.drop_vertical();
+                on_platform.0 = Some(platform_entity);
 
-                if transform.translation.x - player_sprite_half_x <= window_left_border {
-                    transform.translation.x = window_left_border + player_sprite_half_x;
-                    velocity.drop_horizontal();
-                } else if transform.translation.x + player_sprite_half_x >= window_right_border {
-                    transform.translation.x = window_right_border - player_sprite_half_x;
-                    velocity.drop_horizontal();
+                if let Some(player) = &mut player {
+                    player.stomp_combo = 0;
+                    player::update_movement_state(player, &velocity);
                 }
-                player::update_movement_state(&mut player, &velocity);
+                break;
             }
         }
     }
 }
 
-pub fn collisions(
+/// Lowers the player's hurtbox and sprite while crouching or sliding, so they fit under flying
+/// enemies.
+pub fn crouch(mut query: Query<(&Player, &mut Hurtbox, &mut Deformation)>) {
+    for (player, mut hurtbox, mut deformation) in query.iter_mut() {
+        *hurtbox = player.hurtbox();
+        deformation.crouch = if player.is_crouched() {
+            player::HEIGHT - player::CROUCH_HEIGHT
+        } else {
+            0.0
+        };
+    }
+}
+
+/// Ends a ground pound that reached the ground with a shockwave along it.
+pub fn land_ground_pound(
     mut commands: Commands,
-    mut events: EventWriter<PlayerEvent>,
-    player_query: Query<(&Player, &Sprite, &Transform)>,
-    colliders: Query<(Entity, &Collider, &Sprite, &Transform)>,
+    tuning: Res<Tuning>,
+    mut query: Query<(&Player, &mut GroundPound, &Sprite, &Transform)>,
+) {
+    for (player, mut ground_pound, sprite, transform) in query.iter_mut() {
+        if !ground_pound.slamming || !player.is_on_ground() {
+            continue;
+        }
+        ground_pound.slamming = false;
+        ground_pound.cooldown_left = tuning.ground_pound.cooldown;
+
+        let bottom = transform.translation.y - sprite.size.y / 2.0;
+        let position = Vec3::new(
+            transform.translation.x,
+            bottom + player::SHOCKWAVE_HEIGHT / 2.0,
+            0.0,
+        );
+        commands.spawn_bundle(SpriteBundle {
+                sprite: Sprite::new(Vec2::new(
+                    tuning.ground_pound.radius * 2.0,
+                    player::SHOCKWAVE_HEIGHT,
+                )),
+                transform: Transform::from_translation(position),
+                visible: Visible {
+                    is_visible: false,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Shockwave)
+            .insert(GameEntity)
+            .insert(Collider::Shockwave);
+    }
+}
+
+type ColliderShape = (
+    Entity,
+    &'static Sprite,
+    &'static Transform,
+    Option<&'static Interpolated>,
+    Option<&'static Hitbox>,
+    Option<&'static Hurtbox>,
+);
+
+/// Sends a `CollisionEvent` for every hitbox touching another collider's hurtbox, tracking when
+/// contacts start and end between steps.
+pub fn detect_collisions(
+    mut events: EventWriter<CollisionEvent>,
+    mut grid: Local<UniformGrid<Entity>>,
+    mut candidates: Local<Vec<Entity>>,
+    mut contacts: Local<BTreeMap<(Entity, Entity), Vec2>>,
+    mut current_contacts: Local<BTreeMap<(Entity, Entity), Vec2>>,
+    colliders: Query<ColliderShape, With<Collider>>,
 ) {
-    for (_player, player_sprite, player_transform) in player_query.iter() {
-        for (collider_entity, collider, collider_sprite, collider_transform) in colliders.iter() {
-            let collision = collide_aabb::collide(
-                player_transform.translation,
-                player_sprite.size,
-                collider_transform.translation,
-                collider_sprite.size,
+    grid.clear();
+    for (entity, sprite, transform, interpolated, _, hurtbox) in colliders.iter() {
+        let shape = hurtbox.map_or_else(|| Shape::aabb(sprite.size), |hurtbox| hurtbox.0);
+        let (min, max) = shape.swept_bounds(
+            start_translation(transform, interpolated).truncate(),
+            transform.translation.truncate(),
+        );
+        grid.insert(min, max, entity);
+    }
+
+    current_contacts.clear();
+    for (a, a_sprite, a_transform, a_interpolated, a_hitbox, _) in colliders.iter() {
+        let a_shape = a_hitbox.map_or_else(|| Shape::aabb(a_sprite.size), |hitbox| hitbox.0);
+        let a_start = start_translation(a_transform, a_interpolated).truncate();
+        let a_end = a_transform.translation.truncate();
+        let (min, max) = a_shape.swept_bounds(a_start, a_end);
+        candidates.clear();
+        grid.query(min, max, &mut candidates);
+
+        for &b in candidates.iter() {
+            if b == a {
+                continue;
+            }
+            let (_, b_sprite, b_transform, b_interpolated, _, b_hurtbox) = match colliders.get(b) {
+                Ok(collider) => collider,
+                Err(_) => continue,
+            };
+            let b_shape = b_hurtbox.map_or_else(|| Shape::aabb(b_sprite.size), |hurtbox| hurtbox.0);
+
+            let normal = shapes::swept_contact(
+                &a_shape,
+                a_start,
+                a_end,
+                &b_shape,
+                start_translation(b_transform, b_interpolated).truncate(),
+                b_transform.translation.truncate(),
             );
-            if collision.is_some() {
-                match collider {
-                    Collider::Solid => {
-                        events.send(PlayerEvent::Hit);
-                    }
-                    Collider::Award(award) => {
-                        events.send(PlayerEvent::Award(*award));
-                        commands.entity(collider_entity).despawn();
-                    }
+            if let Some(normal) = normal {
+                current_contacts.insert((a, b), normal);
+            }
+        }
+    }
+
+    for (&(a, b), &normal) in current_contacts.iter() {
+        let phase = if contacts.contains_key(&(a, b)) {
+            CollisionPhase::Stay
+        } else {
+            CollisionPhase::Enter
+        };
+        events.send(CollisionEvent {
+            a,
+            b,
+            phase,
+            normal,
+        });
+    }
+    for (&(a, b), &normal) in contacts.iter() {
+        if !current_contacts.contains_key(&(a, b)) {
+            events.send(CollisionEvent {
+                a,
+                b,
+                phase: CollisionPhase::Exit,
+                normal,
+            });
+        }
+    }
+
+    std::mem::swap(&mut *contacts, &mut *current_contacts);
+}
+
+/// Turns the player's contacts into hits, stomps and pickups. Landing mostly from above on an
+/// enemy while falling stomps it instead of getting hit by it.
+pub fn player_collisions(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    mut events: EventWriter<PlayerEvent>,
+    mut contacts: Local<Vec<CollisionEvent>>,
+    mut stomped: Local<Vec<Entity>>,
+    players: Query<(Entity, &Player)>,
+    colliders: Query<(&Collider, Option<&Knockback>)>,
+) {
+    contacts.clear();
+    contacts.extend(
+        collisions
+            .iter()
+            .filter(|collision| collision.phase != CollisionPhase::Exit),
+    );
+
+    stomped.clear();
+    for (player_entity, player) in players.iter() {
+        if player.movement_state != PlayerMovementState::Falling {
+            continue;
+        }
+        for collision in contacts.iter().filter(|collision| collision.a == player_entity) {
+            let normal = collision.normal;
+            if matches!(colliders.get(collision.b), Ok((Collider::Solid, _)))
+                && normal.y > normal.x.abs()
+                && !stomped.contains(&collision.b)
+            {
+                events.send(PlayerEvent::Stomp);
+                commands.entity(collision.b).despawn();
+                stomped.push(collision.b);
+            }
+        }
+    }
+
+    for (player_entity, _) in players.iter() {
+        for collision in contacts.iter().filter(|collision| collision.b == player_entity) {
+            if stomped.contains(&collision.a) {
+                continue;
+            }
+            match (colliders.get(collision.a), collision.phase) {
+                (Ok((Collider::Solid, knockback)), _) => {
+                    events.send(PlayerEvent::Hit {
+                        knockback: knockback.copied(),
+                        away: -collision.normal,
+                    });
+                }
+                (Ok((Collider::Award(award), _)), CollisionPhase::Enter) => {
+                    events.send(PlayerEvent::Award(*award));
+                    commands.entity(collision.a).despawn();
                 }
+                _ => {}
             }
         }
     }
 }
+
+type GroundEnemy = (With<Enemy>, Without<Flying>);
+
+/// Destroys the ground enemies a shockwave hits close to its center and pushes away the others,
+/// then removes the shockwave.
+pub fn shockwave_collisions(
+    mut commands: Commands,
+    tuning: Res<Tuning>,
+    mut collisions: EventReader<CollisionEvent>,
+    mut events: EventWriter<PlayerEvent>,
+    mut contacts: Local<Vec<CollisionEvent>>,
+    shockwaves: Query<(Entity, &Transform), With<Shockwave>>,
+    mut enemies: Query<(&Transform, &mut Velocity, &mut EntityEffects), GroundEnemy>,
+) {
+    contacts.clear();
+    contacts.extend(
+        collisions
+            .iter()
+            .filter(|collision| collision.phase == CollisionPhase::Enter),
+    );
+
+    let knockback = &tuning.ground_pound.knockback;
+    for (shockwave_entity, shockwave_transform) in shockwaves.iter() {
+        let mut destroyed = 0;
+        for collision in contacts.iter().filter(|collision| collision.a == shockwave_entity) {
+            let (transform, mut velocity, mut effects) = match enemies.get_mut(collision.b) {
+                Ok(enemy) => enemy,
+                Err(_) => continue,
+            };
+
+            let offset = transform.translation.x - shockwave_transform.translation.x;
+            if offset.abs() <= tuning.ground_pound.kill_radius {
+                commands.entity(collision.b).despawn();
+                destroyed += 1;
+                continue;
+            }
+
+            // Boost the enemy's own velocity, so it walks on as before once the push is over.
+            velocity.drop_boost();
+            if velocity.horizontal() != 0.0 && knockback.stun_time > 0.0 {
+                let boost = offset.signum() * knockback.speed / velocity.horizontal();
+                effects.active.push(EntityEffectDescriptor::new_temporary(
+                    SpeedBoost::horizontal(boost),
+                    Duration::from_secs_f32(knockback.stun_time),
+                ));
+            }
+            velocity.set_vertical(knockback.lift);
+        }
+
+        events.send(PlayerEvent::GroundPound { destroyed });
+        commands.entity(shockwave_entity).despawn();
+    }
+}
+
+type Falling = (
+    Entity,
+    Option<&'static Player>,
+    Option<&'static mut GroundPound>,
+    &'static mut Velocity,
+    &'static Sprite,
+    &'static mut Transform,
+    Option<&'static mut Interpolated>,
+);
+
+/// Drops entities that fell through a pit out of the screen. The player loses health instead and
+/// is put back on the ground right after the pit, or before it when that's past the window.
+pub fn fall_out_of_world(
+    mut commands: Commands,
+    mut events: EventWriter<PlayerEvent>,
+    pits: Query<(&Sprite, &Transform), With<Pit>>,
+    mut query: Query<Falling, (With<AffectedByGravity>, Without<Pit>)>,
+) {
+    for (entity, player, ground_pound, mut velocity, sprite, mut transform, interpolated) in
+        query.iter_mut()
+    {
+        if transform.translation.y + sprite.size.y / 2.0 >= -world::SCREEN_HEIGHT / 2.0 {
+            continue;
+        }
+
+        if player.is_none() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let pits: Vec<(f32, f32)> = pits
+            .iter()
+            .map(|(pit_sprite, pit_transform)| pit_span(pit_sprite, pit_transform.translation.x))
+            .collect();
+        transform.translation.x = respawn_x(&pits, transform.translation.x, sprite.size.x / 2.0);
+        transform.translation.y = sprite.size.y / 2.0;
+        velocity.reset();
+        if let Some(mut interpolated) = interpolated {
+            *interpolated = Interpolated::new(transform.translation);
+        }
+        if let Some(mut ground_pound) = ground_pound {
+            ground_pound.slamming = false;
+        }
+
+        events.send(PlayerEvent::Fall);
+    }
+}
+
+/// Nearest spot to `x` where an entity `half_width` wide stands on solid ground inside the window,
+/// looking to the right first.
+fn respawn_x(pits: &[(f32, f32)], x: f32, half_width: f32) -> f32 {
+    let max_x = world::SCREEN_WIDTH / 2.0 - half_width;
+    let pit_under = |x: f32| {
+        pits.iter()
+            .copied()
+            .find(|&(left, right)| left - half_width < x && x < right + half_width)
+    };
+
+    let mut right_x = x;
+    while let Some((_, right)) = pit_under(right_x) {
+        right_x = right + half_width;
+    }
+    if right_x <= max_x {
+        return right_x;
+    }
+
+    let mut left_x = x.min(max_x);
+    while let Some((left, _)) = pit_under(left_x) {
+        left_x = left - half_width;
+    }
+    left_x
+}
+
+fn pit_span(sprite: &Sprite, x: f32) -> (f32, f32) {
+    (x - sprite.size.x / 2.0, x + sprite.size.x / 2.0)
+}
+
+/// Whether an entity is fully over one of the pits, with no ground left under it.
+fn over_pit(pits: &[(f32, f32)], x: f32, half_width: f32) -> bool {
+    pits.iter()
+        .any(|&(left, right)| x - half_width >= left && x + half_width <= right)
+}
+
+fn start_translation(transform: &Transform, interpolated: Option<&Interpolated>) -> Vec3 {
+    interpolated.map_or(transform.translation, |interpolated| interpolated.previous)
+}
@@ -0,0 +1,127 @@
+use crate::game::{Game, GameState};
+use crate::random::GameRng;
+use crate::records::{BestScore, Leaderboard, LeaderboardEntry};
+use crate::systems::replay::ReplayPlayer;
+use bevy::prelude::*;
+use bevy::window::ReceivedCharacter;
+use chrono::Local;
+
+const MAX_NAME_LENGTH: usize = 12;
+
+/// The name being typed for a leaderboard entry, if the last score qualified for one.
+#[derive(Default)]
+pub struct NameEntry {
+    pub name: Option<String>,
+}
+
+pub struct RecordsPlugin;
+
+impl Plugin for RecordsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<NameEntry>()
+            .add_startup_system(load_records.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(save_best_score.system())
+                    .with_system(start_name_entry.system()),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::GameOver).with_system(name_entry_input.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver).with_system(cancel_name_entry.system()),
+            );
+    }
+}
+
+fn load_records(mut commands: Commands, mut game: ResMut<Game>) {
+    let best_score = BestScore::load().unwrap_or_else(|err| {
+        warn!("Can't load the best score, starting from scratch: {}", err);
+        BestScore::default()
+    });
+    let leaderboard = Leaderboard::load().unwrap_or_else(|err| {
+        warn!("Can't load the leaderboard, starting from scratch: {}", err);
+        Leaderboard::default()
+    });
+
+    game.best_score = best_score.score;
+    commands.insert_resource(best_score);
+    commands.insert_resource(leaderboard);
+}
+
+fn save_best_score(
+    game: Res<Game>,
+    replay_player: Option<Res<ReplayPlayer>>,
+    mut best_score: ResMut<BestScore>,
+) {
+    // A replayed run was already recorded when it was played.
+    if replay_player.is_some() || game.score <= best_score.score {
+        return;
+    }
+
+    *best_score = BestScore {
+        score: game.score,
+        date: Some(Local::today().naive_local()),
+    };
+    if let Err(err) = best_score.save() {
+        warn!("Can't save the best score: {}", err);
+    }
+}
+
+fn start_name_entry(
+    game: Res<Game>,
+    replay_player: Option<Res<ReplayPlayer>>,
+    leaderboard: Res<Leaderboard>,
+    mut name_entry: ResMut<NameEntry>,
+) {
+    if replay_player.is_none() && leaderboard.qualifies(game.score) {
+        name_entry.name = Some(String::new());
+    }
+}
+
+fn name_entry_input(
+    game: Res<Game>,
+    rng: Res<GameRng>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut characters: EventReader<ReceivedCharacter>,
+    mut name_entry: ResMut<NameEntry>,
+    mut leaderboard: ResMut<Leaderboard>,
+) {
+    let name = match name_entry.name.as_mut() {
+        Some(name) => name,
+        None => return,
+    };
+
+    for event in characters.iter() {
+        if (event.char.is_alphanumeric() || event.char == ' ') && name.len() < MAX_NAME_LENGTH {
+            name.push(event.char);
+        }
+    }
+
+    if input.just_pressed(KeyCode::Back) {
+        name.pop();
+    }
+
+    if input.just_pressed(KeyCode::Escape) {
+        input.reset(KeyCode::Escape);
+        name_entry.name = None;
+    } else if input.just_pressed(KeyCode::Return) && !name.trim().is_empty() {
+        input.reset(KeyCode::Return);
+        leaderboard.insert(LeaderboardEntry {
+            name: name.trim().to_string(),
+            score: game.score,
+            date: Local::today().naive_local(),
+            time_survived: game.time_survived,
+            seed: Some(rng.seed()),
+        });
+        name_entry.name = None;
+
+        if let Err(err) = leaderboard.save() {
+            warn!("Can't save the leaderboard: {}", err);
+        }
+    }
+}
+
+fn cancel_name_entry(mut name_entry: ResMut<NameEntry>) {
+    name_entry.name = None;
+}
@@ -0,0 +1,103 @@
+use crate::game::GameTime;
+use crate::player::PlayerInput;
+use crate::random::GameRng;
+use crate::replay::Replay;
+use crate::tuning::Tuning;
+use bevy::prelude::*;
+
+use std::path::PathBuf;
+
+/// Records every run into a replay file, overwritten when the next run is over.
+pub struct ReplayRecorder {
+    path: PathBuf,
+    replay: Option<Replay>,
+}
+
+impl ReplayRecorder {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, replay: None }
+    }
+
+    /// Starts recording a new run, dropping the one recorded so far.
+    pub fn start(&mut self, rng: &GameRng, time: &GameTime, tuning: &Tuning) {
+        self.replay = Some(Replay::new(rng.seed(), time.delta(), tuning.clone()));
+    }
+}
+
+/// Feeds the inputs of a replay instead of the keyboard.
+pub struct ReplayPlayer {
+    replay: Replay,
+    cursor: usize,
+    input: PlayerInput,
+}
+
+impl ReplayPlayer {
+    pub fn new(replay: Replay) -> Self {
+        Self {
+            replay,
+            cursor: 0,
+            input: PlayerInput::default(),
+        }
+    }
+}
+
+pub fn start_recording(
+    recorder: Option<ResMut<ReplayRecorder>>,
+    rng: Res<GameRng>,
+    time: Res<GameTime>,
+    tuning: Res<Tuning>,
+) {
+    if let Some(mut recorder) = recorder {
+        recorder.start(&rng, &time, &tuning);
+    }
+}
+
+pub fn record_input(
+    recorder: Option<ResMut<ReplayRecorder>>,
+    time: Res<GameTime>,
+    player_input: Res<PlayerInput>,
+) {
+    if let Some(mut recorder) = recorder {
+        if let Some(replay) = recorder.replay.as_mut() {
+            replay.record(time.tick(), *player_input);
+        }
+    }
+}
+
+pub fn save_recording(recorder: Option<ResMut<ReplayRecorder>>) {
+    if let Some(mut recorder) = recorder {
+        if let Some(replay) = recorder.replay.take() {
+            match replay.save(&recorder.path) {
+                Ok(()) => info!("Saved replay to {}", recorder.path.display()),
+                Err(err) => warn!("Can't save replay: {}", err),
+            }
+        }
+    }
+}
+
+pub fn restart_playback(player: Option<ResMut<ReplayPlayer>>) {
+    if let Some(mut player) = player {
+        player.cursor = 0;
+        player.input = PlayerInput::default();
+    }
+}
+
+pub fn play_input(
+    player: Option<ResMut<ReplayPlayer>>,
+    time: Res<GameTime>,
+    mut player_input: ResMut<PlayerInput>,
+) {
+    let mut player = match player {
+        Some(player) => player,
+        None => return,
+    };
+
+    while let Some((tick, input)) = player.replay.inputs.get(player.cursor).copied() {
+        if tick > time.tick() {
+            break;
+        }
+        player.input = input;
+        player.cursor += 1;
+    }
+    *player_input = player.input;
+}
@@ -1,18 +1,45 @@
-use crate::awards::{Award, AwardTimer};
+use crate::awards::{self, AbilityTimer, Award, AwardMaterials, AwardTimer};
 use crate::enemies;
-use crate::enemies::{Enemy, SpawnTimer};
-use crate::game::{GameEntity};
-use crate::player::Player;
-use crate::world::{Collider, Velocity};
+use crate::enemies::{Enemy, Flying, SpawnTimer};
+use crate::effects::EntityEffects;
+use crate::game::{GameEntity, GameTime};
+use crate::pits::{self, Pit};
+use crate::platforms::{self, Platform, PlatformTimer};
+use crate::player::{Abilities, Ability, Player};
+use crate::random::GameRng;
+use crate::shapes::Shape;
+use crate::tuning::Tuning;
+use crate::world::{
+    self, AffectedByGravity, Collider, Deformation, GravityZone, Hitbox, Interpolated, Velocity,
+};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 use std::time::Duration;
 
-pub fn spawn_new_enemy(
+/// Spawners share `GameRng`, their order keeps runs with the same seed identical.
+#[derive(Debug, Hash, PartialEq, Eq, Clone, SystemLabel)]
+pub enum SpawnSystem {
+    Obstacle,
+    Platform,
+    Health,
+}
+
+/// Timers of everything spawned during a run.
+#[derive(SystemParam)]
+pub struct SpawnTimers<'a> {
+    pub enemies: ResMut<'a, SpawnTimer>,
+    pub platforms: ResMut<'a, PlatformTimer>,
+    pub awards: ResMut<'a, AwardTimer>,
+    pub abilities: ResMut<'a, AbilityTimer>,
+}
+
+pub fn spawn_obstacle(
     mut commands: Commands,
-    window: Res<WindowDescriptor>,
-    time: Res<Time>,
+    time: Res<GameTime>,
+    tuning: Res<Tuning>,
     mut spawn_timer: ResMut<SpawnTimer>,
+    mut rng: ResMut<GameRng>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     player_query: Query<&Sprite, With<Player>>,
 ) {
@@ -24,72 +51,234 @@ pub fn spawn_new_enemy(
 
     spawn_timer
         .timer
-        .set_duration(Duration::from_secs_f32(thread_rng().gen_range(2.0, 3.0)));
-    let mut rng = thread_rng();
+        .set_duration(Duration::from_secs_f32(rng.gen_range(
+            tuning.enemies.min_spawn_interval,
+            tuning.enemies.max_spawn_interval,
+        )));
 
-    commands.spawn_bundle(SpriteBundle {
-            sprite: Sprite::new(Vec2::new(enemies::ENEMY_WIDTH, enemies::ENEMY_HEIGHT)),
-            material: materials.add(
-                Color::rgb(
-                    rng.gen_range(0.0, 1.0),
-                    rng.gen_range(0.0, 1.0),
-                    rng.gen_range(0.0, 1.0),
-                ).into(),
-            ),
-            transform: Transform::from_translation(Vec3::new(
-                enemies::INITIAL_POSITION_X,
-                enemies::INITIAL_POSITION_Y,
-                0.0,
-            )),
-            ..Default::default()
-        })
-        .insert(Enemy)
-        .insert(GameEntity)
-        .insert(Velocity::new(Vec2::new(-enemies::VELOCITY_X, enemies::VELOCITY_Y)))
-        .insert(Collider::Solid);
+    let right_edge = if rng.gen_bool(tuning.pits.chance as f64) {
+        spawn_pit(&mut commands, &tuning, &mut rng, &mut materials)
+    } else if rng.gen_bool(tuning.world.zone_chance as f64) {
+        spawn_gravity_zone(&mut commands, &tuning, &mut rng, &mut materials)
+    } else if rng.gen_bool(tuning.flying.chance as f64) {
+        spawn_flying_enemy(&mut commands, &tuning, &mut materials)
+    } else {
+        spawn_enemy(&mut commands, &tuning, &mut rng, &mut materials)
+    };
 
+    // Passing the obstacle is scored when the player touches this sensor right behind it.
     for sprite in player_query.iter() {
+        let sensor_position = Vec3::new(
+            right_edge - enemies::ENEMY_WIDTH / 2.0 + sprite.size.x + 1.0,
+            enemies::INITIAL_POSITION_Y,
+            0.0,
+        );
+
         commands.spawn_bundle(SpriteBundle {
-                sprite: Sprite::new(Vec2::new(enemies::ENEMY_WIDTH, window.height as f32)),
+                sprite: Sprite::new(Vec2::new(enemies::ENEMY_WIDTH, world::SCREEN_HEIGHT)),
                 material: materials.add(Color::NONE.into()),
-                transform: Transform::from_translation(Vec3::new(
-                    enemies::INITIAL_POSITION_X + sprite.size.x + 1.0,
-                    enemies::INITIAL_POSITION_Y,
-                    0.0,
-                )),
+                transform: Transform::from_translation(sensor_position),
                 visible: Visible {
                     is_visible: false,
                     ..Default::default()
                 },
                 ..Default::default()
             })
+            .insert(Interpolated::new(sensor_position))
             .insert(Velocity::new(Vec2::new(
-                -enemies::VELOCITY_X,
+                -tuning.enemies.velocity_x,
                 enemies::VELOCITY_Y,
             )))
-            .insert(Collider::Award(Award::Score(enemies::SCORE)));
+            .insert(Collider::Award(Award::Score(tuning.enemies.score)));
     }
 }
 
-pub fn drop_enemies(
+/// Returns the right edge of the new enemy.
+fn spawn_enemy(
+    commands: &mut Commands,
+    tuning: &Tuning,
+    rng: &mut GameRng,
+    materials: &mut Assets<ColorMaterial>,
+) -> f32 {
+    let initial_position = Vec3::new(enemies::INITIAL_POSITION_X, enemies::INITIAL_POSITION_Y, 0.0);
+    let hitbox_offset_y = (enemies::HITBOX_HEIGHT - enemies::ENEMY_HEIGHT) / 2.0;
+
+    commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(enemies::ENEMY_WIDTH, enemies::ENEMY_HEIGHT)),
+            material: materials.add(
+                Color::rgb(
+                    rng.gen_range(0.0, 1.0),
+                    rng.gen_range(0.0, 1.0),
+                    rng.gen_range(0.0, 1.0),
+                ).into(),
+            ),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        })
+        .insert(Enemy)
+        .insert(GameEntity)
+        .insert(AffectedByGravity::new(tuning.enemies.gravity_scale))
+        .insert(Deformation::default())
+        .insert(Interpolated::new(initial_position))
+        .insert(Velocity::new(Vec2::new(-tuning.enemies.velocity_x, enemies::VELOCITY_Y)))
+        .insert(EntityEffects::default())
+        .insert(Collider::Solid)
+        .insert(tuning.enemies.knockback)
+        .insert(Hitbox(
+            Shape::aabb(Vec2::new(enemies::HITBOX_WIDTH, enemies::HITBOX_HEIGHT))
+                .with_offset(Vec2::new(0.0, hitbox_offset_y)),
+        ));
+
+    initial_position.x + enemies::ENEMY_WIDTH / 2.0
+}
+
+/// Returns the right edge of the new enemy.
+fn spawn_flying_enemy(
+    commands: &mut Commands,
+    tuning: &Tuning,
+    materials: &mut Assets<ColorMaterial>,
+) -> f32 {
+    let initial_position = Vec3::new(
+        (world::SCREEN_WIDTH + enemies::FLYING_WIDTH) / 2.0,
+        tuning.flying.altitude + enemies::FLYING_HEIGHT / 2.0,
+        0.0,
+    );
+
+    commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(enemies::FLYING_WIDTH, enemies::FLYING_HEIGHT)),
+            material: materials.add(Color::rgb(0.6, 0.1, 0.1).into()),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        })
+        .insert(Enemy)
+        .insert(Flying)
+        .insert(GameEntity)
+        .insert(Interpolated::new(initial_position))
+        .insert(Velocity::with_horizontal(-tuning.enemies.velocity_x))
+        .insert(Collider::Solid)
+        .insert(tuning.flying.knockback);
+
+    initial_position.x + enemies::FLYING_WIDTH / 2.0
+}
+
+/// Returns the right edge of the new pit.
+fn spawn_pit(
+    commands: &mut Commands,
+    tuning: &Tuning,
+    rng: &mut GameRng,
+    materials: &mut Assets<ColorMaterial>,
+) -> f32 {
+    let width = rng.gen_range(tuning.pits.min_width, tuning.pits.max_width);
+    // Drawn over the ground, entities falling in disappear in it.
+    let initial_position = Vec3::new((world::SCREEN_WIDTH + width) / 2.0, -pits::DEPTH / 2.0, 0.05);
+
+    commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(width, pits::DEPTH)),
+            material: materials.add(Color::WHITE.into()),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        })
+        .insert(Pit)
+        .insert(GameEntity)
+        .insert(Interpolated::new(initial_position))
+        .insert(Velocity::with_horizontal(-tuning.enemies.velocity_x));
+
+    initial_position.x + width / 2.0
+}
+
+/// Returns the right edge of the new zone.
+fn spawn_gravity_zone(
+    commands: &mut Commands,
+    tuning: &Tuning,
+    rng: &mut GameRng,
+    materials: &mut Assets<ColorMaterial>,
+) -> f32 {
+    let width = rng.gen_range(tuning.world.zone_min_width, tuning.world.zone_max_width);
+    let scale = tuning.world.zone_scales[rng.gen_range(0, tuning.world.zone_scales.len())];
+    let color = if scale < 0.0 {
+        Color::rgba(0.8, 0.3, 1.0, 0.15)
+    } else {
+        Color::rgba(0.3, 0.5, 1.0, 0.15)
+    };
+    // Everything above the ground, tinting whatever is inside.
+    let height = world::SCREEN_HEIGHT / 2.0;
+    let initial_position = Vec3::new((world::SCREEN_WIDTH + width) / 2.0, height / 2.0, 0.02);
+
+    commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(width, height)),
+            material: materials.add(color.into()),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        })
+        .insert(GravityZone { scale })
+        .insert(GameEntity)
+        .insert(Interpolated::new(initial_position))
+        .insert(Velocity::with_horizontal(-tuning.enemies.velocity_x));
+
+    initial_position.x + width / 2.0
+}
+
+type Obstacle = Or<(With<Enemy>, With<Platform>, With<Pit>, With<GravityZone>)>;
+
+/// Despawns obstacles that scrolled out of the screen on the left.
+pub fn drop_passed_obstacles(
     mut commands: Commands,
-    game_window: Res<WindowDescriptor>,
-    query: Query<(Entity, &Sprite, &Transform), With<Enemy>>,
+    query: Query<(Entity, &Sprite, &Transform), Obstacle>,
 ) {
-    for (enemy_entity, sprite, transform) in query.iter() {
-        if transform.translation.x + sprite.size.x < -(game_window.width as f32) / 2.0 {
-            commands.entity(enemy_entity).despawn();
+    for (entity, sprite, transform) in query.iter() {
+        if transform.translation.x + sprite.size.x < -world::SCREEN_WIDTH / 2.0 {
+            commands.entity(entity).despawn();
         }
     }
 }
 
+pub fn spawn_platform(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    tuning: Res<Tuning>,
+    mut timer: ResMut<PlatformTimer>,
+    mut rng: ResMut<GameRng>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    timer.timer.tick(time.delta());
+
+    if !timer.timer.finished() {
+        return;
+    }
+
+    timer
+        .timer
+        .set_duration(Duration::from_secs_f32(rng.gen_range(
+            tuning.platforms.min_spawn_interval,
+            tuning.platforms.max_spawn_interval,
+        )));
+
+    let top = rng.gen_range(tuning.platforms.min_height, tuning.platforms.max_height);
+    let initial_position = Vec3::new(
+        platforms::INITIAL_POSITION_X,
+        top - platforms::HEIGHT / 2.0,
+        0.0,
+    );
+
+    commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(platforms::WIDTH, platforms::HEIGHT)),
+            material: materials.add(Color::rgb(0.45, 0.35, 0.25).into()),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        })
+        .insert(Platform)
+        .insert(GameEntity)
+        .insert(Interpolated::new(initial_position))
+        .insert(Velocity::with_horizontal(-tuning.enemies.velocity_x))
+        .insert(Collider::Platform);
+}
+
 pub fn spawn_health(
     mut commands: Commands,
-    window: Res<WindowDescriptor>,
-    time: Res<Time>,
-    asset_server: Res<AssetServer>,
+    time: Res<GameTime>,
+    award_materials: Res<AwardMaterials>,
     mut timer: ResMut<AwardTimer>,
-    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut rng: ResMut<GameRng>,
     players: Query<&Player>,
 ) {
     for player in players.iter() {
@@ -103,28 +292,76 @@ pub fn spawn_health(
         return;
     }
 
-    timer.refill();
+    timer.refill(&mut *rng);
 
-    let mut rng = rand::thread_rng();
     let mut health: u8 = 1;
     if rng.gen_bool(0.25) {
         health = 2;
     }
 
-    let texture_handle = asset_server.load("sprites/health.png");
     let width = 48.0 + (health - 1) as f32 * 16.0;
     let height = 48.0 + (health - 1) as f32 * 16.0;
 
-    let initial_x = (window.width as f32 + width) / 2.0;
-    let initial_y = height / 2.0;
+    let initial_position = Vec3::new((world::SCREEN_WIDTH + width) / 2.0, height / 2.0, 0.0);
 
     commands.spawn_bundle(SpriteBundle {
             sprite: Sprite::new(Vec2::new(width, height)),
-            material: materials.add(texture_handle.into()),
-            transform: Transform::from_translation(Vec3::new(initial_x, initial_y, 0.0)),
+            material: award_materials.health.clone(),
+            transform: Transform::from_translation(initial_position),
+            ..Default::default()
+        })
+        .insert(GameEntity)
+        .insert(Interpolated::new(initial_position))
+        .insert(Velocity::with_horizontal(-300.0))
+        .insert(Collider::Award(Award::Health(health)))
+        .insert(Hitbox(Shape::circle(width * awards::PICKUP_RADIUS)));
+}
+
+pub fn spawn_ability(
+    mut commands: Commands,
+    time: Res<GameTime>,
+    mut timer: ResMut<AbilityTimer>,
+    mut rng: ResMut<GameRng>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    players: Query<&Abilities, With<Player>>,
+) {
+    let mut locked = Vec::new();
+    for abilities in players.iter() {
+        for &ability in Ability::ALL.iter() {
+            if !abilities.get(ability).unlocked {
+                locked.push(ability);
+            }
+        }
+    }
+    if locked.is_empty() {
+        return;
+    }
+
+    timer.0.timer.tick(time.delta());
+    if !timer.0.timer.finished() {
+        return;
+    }
+
+    timer.0.refill(&mut *rng);
+
+    let ability = locked[rng.gen_range(0, locked.len())];
+    let color = match ability {
+        Ability::DoubleJump => Color::rgb(0.3, 0.7, 1.0),
+        Ability::AirDash => Color::rgb(1.0, 0.6, 0.2),
+    };
+
+    let size = awards::ABILITY_PICKUP_SIZE;
+    let initial_position = Vec3::new((world::SCREEN_WIDTH + size) / 2.0, size / 2.0, 0.0);
+
+    commands.spawn_bundle(SpriteBundle {
+            sprite: Sprite::new(Vec2::new(size, size)),
+            material: materials.add(color.into()),
+            transform: Transform::from_translation(initial_position),
             ..Default::default()
         })
         .insert(GameEntity)
+        .insert(Interpolated::new(initial_position))
         .insert(Velocity::with_horizontal(-300.0))
-        .insert(Collider::Award(Award::Health(health)));
+        .insert(Collider::Award(Award::Ability(ability)))
+        .insert(Hitbox(Shape::circle(size * awards::PICKUP_RADIUS)));
 }
@@ -0,0 +1,602 @@
+use crate::player::Ability;
+use crate::world::Knockback;
+use crate::{awards, enemies, pits, platforms, player, world};
+use serde::{Deserialize, Serialize};
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_PATH: &str = "assets/tuning.ron";
+
+/// Gameplay constants designers can change without recompiling, see `assets/tuning.ron`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Tuning {
+    pub player: PlayerTuning,
+    pub abilities: AbilityTuning,
+    pub ground_pound: GroundPoundTuning,
+    pub enemies: EnemyTuning,
+    pub flying: FlyingTuning,
+    pub pits: PitTuning,
+    pub platforms: PlatformTuning,
+    pub awards: AwardTuning,
+    pub world: WorldTuning,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlayerTuning {
+    pub jump_velocity: f32,
+    /// Top running speed, reached with the accelerations below.
+    pub movement_velocity: f32,
+    pub ground_acceleration: f32,
+    pub ground_deceleration: f32,
+    pub air_acceleration: f32,
+    pub air_deceleration: f32,
+    pub stomp_velocity: f32,
+    pub gravity_scale: f32,
+    pub jump_cut: f32,
+    pub coyote_time: f32,
+    pub jump_buffer_time: f32,
+    /// Speed of a slide started by crouching while running, kept for `slide_time`.
+    pub slide_velocity: f32,
+    pub slide_time: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AbilityTuning {
+    /// Abilities unlocked without a pickup.
+    pub start_with: Vec<Ability>,
+    pub double_jump_velocity: f32,
+    pub double_jump_charges: u32,
+    pub air_dash_boost: f32,
+    pub air_dash_duration: f32,
+    pub air_dash_charges: u32,
+    pub min_pickup_interval: f32,
+    pub max_pickup_interval: f32,
+}
+
+impl AbilityTuning {
+    pub fn charges(&self, ability: Ability) -> u32 {
+        match ability {
+            Ability::DoubleJump => self.double_jump_charges,
+            Ability::AirDash => self.air_dash_charges,
+        }
+    }
+}
+
+/// Down in the air slams the player down, landing sends out a shockwave along the ground.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroundPoundTuning {
+    pub velocity: f32,
+    pub cooldown: f32,
+    /// Ground enemies closer than this to the landing are destroyed.
+    pub kill_radius: f32,
+    /// Ground enemies further out but closer than this are pushed away with `knockback`.
+    pub radius: f32,
+    pub knockback: Knockback,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EnemyTuning {
+    pub velocity_x: f32,
+    pub gravity_scale: f32,
+    pub score: f32,
+    pub stomp_score: f32,
+    pub knockback: Knockback,
+    pub initial_spawn_delay: f32,
+    pub min_spawn_interval: f32,
+    pub max_spawn_interval: f32,
+}
+
+/// Flying enemies are spawned instead of walking ones, see `chance`. They move with the walking
+/// ones and only a crouching player fits under them.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlyingTuning {
+    pub chance: f32,
+    pub altitude: f32,
+    pub knockback: Knockback,
+}
+
+/// Pits are spawned instead of enemies, see `chance`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PitTuning {
+    pub chance: f32,
+    pub min_width: f32,
+    pub max_width: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PlatformTuning {
+    pub min_height: f32,
+    pub max_height: f32,
+    pub initial_spawn_delay: f32,
+    pub min_spawn_interval: f32,
+    pub max_spawn_interval: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AwardTuning {
+    pub min_health_interval: f32,
+    pub max_health_interval: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct WorldTuning {
+    pub gravity: f32,
+    /// Gravity zones are spawned instead of enemies, see `zone_chance`.
+    pub zone_chance: f32,
+    pub zone_min_width: f32,
+    pub zone_max_width: f32,
+    /// Each zone scales gravity by one of these, negative ones flip it.
+    pub zone_scales: Vec<f32>,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Self {
+            player: PlayerTuning {
+                jump_velocity: player::VELOCITY_ON_JUMP,
+                movement_velocity: player::MOVEMENT_VELOCITY,
+                ground_acceleration: player::GROUND_ACCELERATION,
+                ground_deceleration: player::GROUND_DECELERATION,
+                air_acceleration: player::AIR_ACCELERATION,
+                air_deceleration: player::AIR_DECELERATION,
+                stomp_velocity: player::VELOCITY_ON_STOMP,
+                gravity_scale: 1.0,
+                jump_cut: player::JUMP_CUT,
+                coyote_time: player::COYOTE_TIME,
+                jump_buffer_time: player::JUMP_BUFFER_TIME,
+                slide_velocity: player::SLIDE_VELOCITY,
+                slide_time: player::SLIDE_TIME,
+            },
+            abilities: AbilityTuning {
+                start_with: Vec::new(),
+                double_jump_velocity: player::DOUBLE_JUMP_VELOCITY,
+                double_jump_charges: player::DOUBLE_JUMP_CHARGES,
+                air_dash_boost: player::AIR_DASH_BOOST,
+                air_dash_duration: player::AIR_DASH_DURATION,
+                air_dash_charges: player::AIR_DASH_CHARGES,
+                min_pickup_interval: awards::MIN_ABILITY_INTERVAL,
+                max_pickup_interval: awards::MAX_ABILITY_INTERVAL,
+            },
+            ground_pound: GroundPoundTuning {
+                velocity: player::GROUND_POUND_VELOCITY,
+                cooldown: player::GROUND_POUND_COOLDOWN,
+                kill_radius: player::SHOCKWAVE_KILL_RADIUS,
+                radius: player::SHOCKWAVE_RADIUS,
+                knockback: Knockback {
+                    speed: player::SHOCKWAVE_KNOCKBACK_SPEED,
+                    lift: player::SHOCKWAVE_KNOCKBACK_LIFT,
+                    stun_time: player::SHOCKWAVE_KNOCKBACK_TIME,
+                },
+            },
+            enemies: EnemyTuning {
+                velocity_x: enemies::VELOCITY_X,
+                gravity_scale: 1.0,
+                score: enemies::SCORE,
+                stomp_score: enemies::STOMP_SCORE,
+                knockback: Knockback {
+                    speed: enemies::KNOCKBACK_SPEED,
+                    lift: enemies::KNOCKBACK_LIFT,
+                    stun_time: enemies::HIT_STUN_TIME,
+                },
+                initial_spawn_delay: enemies::INITIAL_SPAWN_DELAY,
+                min_spawn_interval: enemies::MIN_SPAWN_INTERVAL,
+                max_spawn_interval: enemies::MAX_SPAWN_INTERVAL,
+            },
+            flying: FlyingTuning {
+                chance: enemies::FLYING_CHANCE,
+                altitude: enemies::FLYING_ALTITUDE,
+                knockback: Knockback {
+                    speed: enemies::FLYING_KNOCKBACK_SPEED,
+                    lift: enemies::FLYING_KNOCKBACK_LIFT,
+                    stun_time: enemies::FLYING_HIT_STUN_TIME,
+                },
+            },
+            pits: PitTuning {
+                chance: pits::CHANCE,
+                min_width: pits::MIN_WIDTH,
+                max_width: pits::MAX_WIDTH,
+            },
+            platforms: PlatformTuning {
+                min_height: platforms::MIN_HEIGHT,
+                max_height: platforms::MAX_HEIGHT,
+                initial_spawn_delay: platforms::INITIAL_SPAWN_DELAY,
+                min_spawn_interval: platforms::MIN_SPAWN_INTERVAL,
+                max_spawn_interval: platforms::MAX_SPAWN_INTERVAL,
+            },
+            awards: AwardTuning {
+                min_health_interval: awards::MIN_HEALTH_INTERVAL,
+                max_health_interval: awards::MAX_HEALTH_INTERVAL,
+            },
+            world: WorldTuning {
+                gravity: world::GRAVITY,
+                zone_chance: world::ZONE_CHANCE,
+                zone_min_width: world::ZONE_MIN_WIDTH,
+                zone_max_width: world::ZONE_MAX_WIDTH,
+                zone_scales: world::ZONE_SCALES.to_vec(),
+            },
+        }
+    }
+}
+
+impl Tuning {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TuningError> {
+        let path = path.as_ref();
+        let contents =
+            fs::read_to_string(path).map_err(|err| TuningError::Io(path.to_owned(), err))?;
+        Self::parse(&contents).map_err(|err| err.in_file(path))
+    }
+
+    pub fn parse(contents: &str) -> Result<Self, TuningError> {
+        let tuning: Self = ron::de::from_str(contents).map_err(TuningError::Parse)?;
+        tuning.validate()?;
+        Ok(tuning)
+    }
+
+    pub fn validate(&self) -> Result<(), TuningError> {
+        positive("player.jump_velocity", self.player.jump_velocity)?;
+        positive("player.movement_velocity", self.player.movement_velocity)?;
+        positive("player.ground_acceleration", self.player.ground_acceleration)?;
+        non_negative("player.ground_deceleration", self.player.ground_deceleration)?;
+        non_negative("player.air_acceleration", self.player.air_acceleration)?;
+        non_negative("player.air_deceleration", self.player.air_deceleration)?;
+        positive("player.stomp_velocity", self.player.stomp_velocity)?;
+        positive("player.gravity_scale", self.player.gravity_scale)?;
+        fraction("player.jump_cut", self.player.jump_cut)?;
+        non_negative("player.coyote_time", self.player.coyote_time)?;
+        non_negative("player.jump_buffer_time", self.player.jump_buffer_time)?;
+        positive("player.slide_velocity", self.player.slide_velocity)?;
+        non_negative("player.slide_time", self.player.slide_time)?;
+        positive("abilities.double_jump_velocity", self.abilities.double_jump_velocity)?;
+        positive("abilities.air_dash_boost", self.abilities.air_dash_boost)?;
+        positive("abilities.air_dash_duration", self.abilities.air_dash_duration)?;
+        positive("abilities.min_pickup_interval", self.abilities.min_pickup_interval)?;
+        greater(
+            "abilities.max_pickup_interval",
+            self.abilities.max_pickup_interval,
+            "abilities.min_pickup_interval",
+            self.abilities.min_pickup_interval,
+        )?;
+        positive("ground_pound.velocity", self.ground_pound.velocity)?;
+        non_negative("ground_pound.cooldown", self.ground_pound.cooldown)?;
+        non_negative("ground_pound.kill_radius", self.ground_pound.kill_radius)?;
+        not_less(
+            "ground_pound.radius",
+            self.ground_pound.radius,
+            "ground_pound.kill_radius",
+            self.ground_pound.kill_radius,
+        )?;
+        non_negative("ground_pound.knockback.speed", self.ground_pound.knockback.speed)?;
+        non_negative("ground_pound.knockback.lift", self.ground_pound.knockback.lift)?;
+        non_negative("ground_pound.knockback.stun_time", self.ground_pound.knockback.stun_time)?;
+        positive("enemies.velocity_x", self.enemies.velocity_x)?;
+        positive("enemies.gravity_scale", self.enemies.gravity_scale)?;
+        non_negative("enemies.score", self.enemies.score)?;
+        non_negative("enemies.stomp_score", self.enemies.stomp_score)?;
+        non_negative("enemies.knockback.speed", self.enemies.knockback.speed)?;
+        non_negative("enemies.knockback.lift", self.enemies.knockback.lift)?;
+        non_negative("enemies.knockback.stun_time", self.enemies.knockback.stun_time)?;
+        non_negative("enemies.initial_spawn_delay", self.enemies.initial_spawn_delay)?;
+        positive("enemies.min_spawn_interval", self.enemies.min_spawn_interval)?;
+        greater(
+            "enemies.max_spawn_interval",
+            self.enemies.max_spawn_interval,
+            "enemies.min_spawn_interval",
+            self.enemies.min_spawn_interval,
+        )?;
+        fraction("flying.chance", self.flying.chance)?;
+        non_negative("flying.altitude", self.flying.altitude)?;
+        non_negative("flying.knockback.speed", self.flying.knockback.speed)?;
+        non_negative("flying.knockback.lift", self.flying.knockback.lift)?;
+        non_negative("flying.knockback.stun_time", self.flying.knockback.stun_time)?;
+        fraction("pits.chance", self.pits.chance)?;
+        positive("pits.min_width", self.pits.min_width)?;
+        greater(
+            "pits.max_width",
+            self.pits.max_width,
+            "pits.min_width",
+            self.pits.min_width,
+        )?;
+        positive("platforms.min_height", self.platforms.min_height)?;
+        greater(
+            "platforms.max_height",
+            self.platforms.max_height,
+            "platforms.min_height",
+            self.platforms.min_height,
+        )?;
+        non_negative("platforms.initial_spawn_delay", self.platforms.initial_spawn_delay)?;
+        positive("platforms.min_spawn_interval", self.platforms.min_spawn_interval)?;
+        greater(
+            "platforms.max_spawn_interval",
+            self.platforms.max_spawn_interval,
+            "platforms.min_spawn_interval",
+            self.platforms.min_spawn_interval,
+        )?;
+        positive("awards.min_health_interval", self.awards.min_health_interval)?;
+        greater(
+            "awards.max_health_interval",
+            self.awards.max_health_interval,
+            "awards.min_health_interval",
+            self.awards.min_health_interval,
+        )?;
+        positive("world.gravity", self.world.gravity)?;
+        fraction("world.zone_chance", self.world.zone_chance)?;
+        positive("world.zone_min_width", self.world.zone_min_width)?;
+        greater(
+            "world.zone_max_width",
+            self.world.zone_max_width,
+            "world.zone_min_width",
+            self.world.zone_min_width,
+        )?;
+        if self.world.zone_chance > 0.0 && self.world.zone_scales.is_empty() {
+            return Err(TuningError::Invalid {
+                field: "world.zone_scales",
+                value: 0.0,
+                reason: String::from("must not be empty while world.zone_chance is above 0"),
+            });
+        }
+        for &scale in &self.world.zone_scales {
+            finite("world.zone_scales", scale)?;
+        }
+        Ok(())
+    }
+}
+
+fn positive(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if value > 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            value,
+            reason: String::from("must be greater than 0"),
+        })
+    }
+}
+
+fn non_negative(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if value >= 0.0 && value.is_finite() {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            value,
+            reason: String::from("must not be negative"),
+        })
+    }
+}
+
+fn finite(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            value,
+            reason: String::from("must be a finite number"),
+        })
+    }
+}
+
+fn fraction(field: &'static str, value: f32) -> Result<(), TuningError> {
+    if (0.0..=1.0).contains(&value) {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            value,
+            reason: String::from("must be between 0 and 1"),
+        })
+    }
+}
+
+fn not_less(
+    field: &'static str,
+    value: f32,
+    other_field: &'static str,
+    other_value: f32,
+) -> Result<(), TuningError> {
+    if value >= other_value && value.is_finite() {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            value,
+            reason: format!("must not be less than {} = {}", other_field, other_value),
+        })
+    }
+}
+
+fn greater(
+    field: &'static str,
+    value: f32,
+    other_field: &'static str,
+    other_value: f32,
+) -> Result<(), TuningError> {
+    if value > other_value && value.is_finite() {
+        Ok(())
+    } else {
+        Err(TuningError::Invalid {
+            field,
+            value,
+            reason: format!("must be greater than {} = {}", other_field, other_value),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum TuningError {
+    Io(PathBuf, std::io::Error),
+    Parse(ron::Error),
+    Invalid {
+        field: &'static str,
+        value: f32,
+        reason: String,
+    },
+    InFile(PathBuf, Box<TuningError>),
+}
+
+impl TuningError {
+    fn in_file(self, path: &Path) -> Self {
+        TuningError::InFile(path.to_owned(), Box::new(self))
+    }
+}
+
+impl fmt::Display for TuningError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TuningError::Io(path, err) => {
+                write!(f, "can't read tuning file {}: {}", path.display(), err)
+            }
+            TuningError::Parse(err) => write!(f, "malformed tuning: {}", err),
+            TuningError::Invalid {
+                field,
+                value,
+                reason,
+            } => write!(f, "invalid tuning value {} = {}: {}", field, value, reason),
+            TuningError::InFile(path, err) => write!(f, "{}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for TuningError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every range a spawner picks random values from, by the field of its upper end.
+    const RANGES: [&str; 7] = [
+        "abilities.max_pickup_interval",
+        "enemies.max_spawn_interval",
+        "pits.max_width",
+        "platforms.max_height",
+        "platforms.max_spawn_interval",
+        "awards.max_health_interval",
+        "world.zone_max_width",
+    ];
+
+    fn range<'a>(tuning: &'a mut Tuning, max_field: &str) -> (&'a mut f32, &'a mut f32) {
+        match max_field {
+            "abilities.max_pickup_interval" => (
+                &mut tuning.abilities.min_pickup_interval,
+                &mut tuning.abilities.max_pickup_interval,
+            ),
+            "enemies.max_spawn_interval" => (
+                &mut tuning.enemies.min_spawn_interval,
+                &mut tuning.enemies.max_spawn_interval,
+            ),
+            "pits.max_width" => (&mut tuning.pits.min_width, &mut tuning.pits.max_width),
+            "platforms.max_height" => (
+                &mut tuning.platforms.min_height,
+                &mut tuning.platforms.max_height,
+            ),
+            "platforms.max_spawn_interval" => (
+                &mut tuning.platforms.min_spawn_interval,
+                &mut tuning.platforms.max_spawn_interval,
+            ),
+            "awards.max_health_interval" => (
+                &mut tuning.awards.min_health_interval,
+                &mut tuning.awards.max_health_interval,
+            ),
+            "world.zone_max_width" => (
+                &mut tuning.world.zone_min_width,
+                &mut tuning.world.zone_max_width,
+            ),
+            _ => unreachable!("unknown range {}", max_field),
+        }
+    }
+
+    fn invalid_field(tuning: &Tuning) -> Option<&'static str> {
+        match tuning.validate() {
+            Ok(()) => None,
+            Err(TuningError::Invalid { field, .. }) => Some(field),
+            Err(err) => panic!("unexpected error: {}", err),
+        }
+    }
+
+    #[test]
+    fn default_tuning_is_valid() {
+        assert_eq!(invalid_field(&Tuning::default()), None);
+    }
+
+    #[test]
+    fn tuning_file_is_valid() {
+        if let Err(err) = Tuning::load(DEFAULT_PATH) {
+            panic!("{}", err);
+        }
+    }
+
+    #[test]
+    fn ranges_without_room_between_min_and_max_are_rejected() {
+        for &max_field in RANGES.iter() {
+            for &offset in &[0.0, -1.0] {
+                let mut tuning = Tuning::default();
+                let (min, max) = range(&mut tuning, max_field);
+                *max = *min + offset;
+                assert_eq!(
+                    invalid_field(&tuning),
+                    Some(max_field),
+                    "{} = min + {}",
+                    max_field,
+                    offset
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ranges_starting_at_zero_or_below_are_rejected() {
+        for &max_field in RANGES.iter() {
+            for &value in &[0.0, -1.0] {
+                let mut tuning = Tuning::default();
+                let (min, _) = range(&mut tuning, max_field);
+                *min = value;
+                assert!(invalid_field(&tuning).is_some(), "{} = {}", max_field, value);
+            }
+        }
+    }
+
+    #[test]
+    fn negative_and_zero_values_are_rejected() {
+        let mut tuning = Tuning::default();
+        tuning.player.jump_velocity = -1.0;
+        assert_eq!(invalid_field(&tuning), Some("player.jump_velocity"));
+
+        let mut tuning = Tuning::default();
+        tuning.player.gravity_scale = 0.0;
+        assert_eq!(invalid_field(&tuning), Some("player.gravity_scale"));
+
+        let mut tuning = Tuning::default();
+        tuning.enemies.initial_spawn_delay = -1.0;
+        assert_eq!(invalid_field(&tuning), Some("enemies.initial_spawn_delay"));
+
+        let mut tuning = Tuning::default();
+        tuning.player.coyote_time = 0.0;
+        assert_eq!(invalid_field(&tuning), None);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected() {
+        let contents = ron::ser::to_string(&Tuning::default()).unwrap();
+        Tuning::parse(&contents).unwrap();
+
+        let nested = contents.replacen("(player:(", "(player:(jump_height:1.0,", 1);
+        assert_ne!(nested, contents);
+        assert!(matches!(Tuning::parse(&nested), Err(TuningError::Parse(_))));
+
+        let top_level = format!("{},speed:1.0)", &contents[..contents.len() - 1]);
+        assert!(matches!(Tuning::parse(&top_level), Err(TuningError::Parse(_))));
+    }
+}
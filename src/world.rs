@@ -1,12 +1,30 @@
 use crate::awards::Award;
 use crate::player;
-use bevy::prelude::Vec2;
+use crate::shapes::Shape;
+use bevy::prelude::{Entity, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
 
 pub const SCREEN_WIDTH: f32 = 1280.0;
 pub const SCREEN_HEIGHT: f32 = 720.0;
 
 pub const GRAVITY: f32 = player::VELOCITY_ON_JUMP * 4.0;
 
+/// Chance that an obstacle is a gravity zone instead of an enemy.
+pub const ZONE_CHANCE: f32 = 0.1;
+pub const ZONE_MIN_WIDTH: f32 = 300.0;
+pub const ZONE_MAX_WIDTH: f32 = 500.0;
+/// Gravity scales zones are picked from: low and inverted.
+pub const ZONE_SCALES: [f32; 2] = [0.4, -0.5];
+
+/// Vertical velocity change below which sprites don't deform.
+pub const DEFORMATION_THRESHOLD: f32 = 100.0;
+/// Pixels of deformation per unit of vertical velocity change.
+pub const DEFORMATION_PER_VELOCITY: f32 = 0.02;
+pub const MAX_DEFORMATION: f32 = 20.0;
+pub const HIT_DEFORMATION: f32 = 16.0;
+pub const DEFORMATION_STIFFNESS: f32 = 300.0;
+pub const DEFORMATION_DAMPING: f32 = 20.0;
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct Velocity {
     base: Vec2,
@@ -47,6 +65,12 @@ impl Velocity {
         self.boost.x = 1.0;
     }
 
+    /// Moves the base horizontal velocity towards `target` by at most `max_delta`, keeping the
+    /// boost on top of it.
+    pub fn approach_horizontal(&mut self, target: f32, max_delta: f32) {
+        self.base.x += (target - self.base.x).max(-max_delta).min(max_delta);
+    }
+
     pub fn drop_horizontal(&mut self) {
         self.set_horizontal(0.0)
     }
@@ -90,17 +114,178 @@ impl Default for Gravity {
     }
 }
 
-pub struct AffectedByGravity;
+/// Pulled by `Gravity`, scaled per entity.
+pub struct AffectedByGravity {
+    pub scale: f32,
+}
+
+impl AffectedByGravity {
+    pub fn new(scale: f32) -> Self {
+        Self { scale }
+    }
+}
+
+impl Default for AffectedByGravity {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+/// Region scaling gravity for entities inside it. A negative scale flips it upwards.
+pub struct GravityZone {
+    pub scale: f32,
+}
 
+/// Platform the entity is standing on. Gravity doesn't pull it down while there is one.
+#[derive(Default)]
+pub struct OnPlatform(pub Option<Entity>);
+
+/// Translation at the start of the last simulation step. Rendering blends it with the current
+/// one, so movement stays smooth when frames and simulation steps don't line up.
+pub struct Interpolated {
+    pub previous: Vec3,
+    pub current: Vec3,
+}
+
+impl Interpolated {
+    pub fn new(translation: Vec3) -> Self {
+        Self {
+            previous: translation,
+            current: translation,
+        }
+    }
+}
+
+/// Squash and stretch: how far each side of a sprite is pushed out from its rest position. Only
+/// rendering uses it, colliders keep their shapes.
 #[derive(Default)]
 pub struct Deformation {
     pub left: f32,
     pub right: f32,
     pub top: f32,
     pub bottom: f32,
+    /// How far below its usual place the top side rests, to crouch.
+    pub crouch: f32,
+    /// Velocity at the end of the last step. Sudden changes to it deform the sprite.
+    pub last_velocity: Vec2,
+    /// How fast each side moves, in the order left, right, top, bottom.
+    rates: [f32; 4],
+}
+
+impl Deformation {
+    /// Lower and wider, the bottom side stays in place.
+    pub fn squash(&mut self, amount: f32) {
+        self.top -= amount;
+        self.left += amount / 2.0;
+        self.right += amount / 2.0;
+    }
+
+    /// Taller and narrower, the bottom side stays in place.
+    pub fn stretch(&mut self, amount: f32) {
+        self.squash(-amount);
+    }
+
+    /// Narrower and taller around the center.
+    pub fn squeeze(&mut self, amount: f32) {
+        self.left -= amount / 2.0;
+        self.right -= amount / 2.0;
+        self.top += amount / 2.0;
+        self.bottom += amount / 2.0;
+    }
+
+    /// Moves every side back towards rest with a damped spring.
+    pub fn spring_back(&mut self, stiffness: f32, damping: f32, delta_seconds: f32) {
+        let mut sides = [self.left, self.right, self.top, self.bottom];
+        let rest = [0.0, 0.0, -self.crouch, 0.0];
+        for ((side, rate), rest) in sides.iter_mut().zip(self.rates.iter_mut()).zip(rest.iter()) {
+            *rate += (-stiffness * (*side - rest) - damping * *rate) * delta_seconds;
+            *side += *rate * delta_seconds;
+        }
+        let [left, right, top, bottom] = sides;
+        self.left = left;
+        self.right = right;
+        self.top = top;
+        self.bottom = bottom;
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn scale(&self, size: Vec2) -> Vec3 {
+        Vec3::new(
+            ((size.x + self.left + self.right) / size.x).max(0.1),
+            ((size.y + self.top + self.bottom) / size.y).max(0.1),
+            1.0,
+        )
+    }
+
+    pub fn offset(&self) -> Vec3 {
+        Vec3::new(
+            (self.right - self.left) / 2.0,
+            (self.top - self.bottom) / 2.0,
+            0.0,
+        )
+    }
 }
 
 pub enum Collider {
+    Player,
     Solid,
     Award(Award),
+    Platform,
+    Shockwave,
+}
+
+/// Where a collider hits others or gets picked up. Colliders without one use their sprite's box.
+#[derive(Debug, Clone, Copy)]
+pub struct Hitbox(pub Shape);
+
+/// Where a collider gets hit or picks things up. Colliders without one use their sprite's box.
+#[derive(Debug, Clone, Copy)]
+pub struct Hurtbox(pub Shape);
+
+/// How hard a collider's hitbox pushes the player away, and for how long their input is ignored
+/// after.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Knockback {
+    pub speed: f32,
+    /// Upward speed added on top, to lift the player off the ground.
+    pub lift: f32,
+    pub stun_time: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CollisionPhase {
+    /// First step the colliders touch.
+    Enter,
+    /// Every following step they still touch.
+    Stay,
+    /// First step they don't touch anymore, or one of them is gone.
+    Exit,
+}
+
+/// Contact of the hitbox of `a` with the hurtbox of `b`.
+#[derive(Debug, Clone, Copy)]
+pub struct CollisionEvent {
+    pub a: Entity,
+    pub b: Entity,
+    pub phase: CollisionPhase,
+    /// Side of `b` that `a` touches, pointing from `b` towards `a`.
+    pub normal: Vec2,
+}
+
+impl CollisionEvent {
+    /// The other entity of the pair and the normal pointing from it towards `entity`, if `entity`
+    /// is part of the pair.
+    pub fn other(&self, entity: Entity) -> Option<(Entity, Vec2)> {
+        if self.a == entity {
+            Some((self.b, self.normal))
+        } else if self.b == entity {
+            Some((self.a, -self.normal))
+        } else {
+            None
+        }
+    }
 }
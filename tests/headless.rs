@@ -0,0 +1,54 @@
+//! Runs with the same seed and inputs have to play out the same way, see `HeadlessGame`.
+
+use jump_or_die::game::{self, GameState};
+use jump_or_die::headless::HeadlessGame;
+use jump_or_die::player::PlayerInput;
+use jump_or_die::tuning::Tuning;
+
+use std::time::Duration;
+
+const TICKS: u64 = 1_200;
+
+/// Runs right, jumping and dashing now and then, and crouches once in a while.
+fn scripted_input(tick: u64) -> PlayerInput {
+    PlayerInput {
+        jump: tick % 45 < 12,
+        left: tick % 300 >= 240,
+        right: tick % 300 < 120,
+        down: tick % 200 >= 190,
+        dash: tick % 150 == 0,
+    }
+}
+
+fn play(seed: u64) -> HeadlessGame {
+    let mut game = HeadlessGame::new(
+        Duration::from_secs_f64(game::SIMULATION_STEP),
+        Some(seed),
+        Tuning::default(),
+    );
+    for tick in 0..TICKS {
+        if *game.state() == GameState::GameOver {
+            break;
+        }
+        game.set_input(scripted_input(tick));
+        game.tick();
+    }
+    game
+}
+
+#[test]
+fn same_seed_and_inputs_play_out_the_same() {
+    let first = play(42);
+    let second = play(42);
+    assert!(first.game().time_survived > 0.0);
+
+    assert_eq!(first.seed(), second.seed());
+    assert_eq!(first.state(), second.state());
+    assert_eq!(first.game().score, second.game().score);
+    assert_eq!(first.game().time_survived, second.game().time_survived);
+    assert_eq!(first.player().health, second.player().health);
+    assert_eq!(
+        first.player_transform().translation,
+        second.player_transform().translation
+    );
+}
@@ -0,0 +1,58 @@
+//! A recorded run has to play back the same way, see `Replay`.
+
+use jump_or_die::game::{self, GameState};
+use jump_or_die::headless::HeadlessGame;
+use jump_or_die::player::PlayerInput;
+use jump_or_die::replay::Replay;
+use jump_or_die::tuning::Tuning;
+
+use std::fs;
+use std::time::Duration;
+
+const MAX_TICKS: u64 = 60 * 60 * 10;
+/// Ticks with scripted input, the player stands still afterwards until something kills them.
+const SCRIPTED_TICKS: u64 = 600;
+
+fn scripted_input(tick: u64) -> PlayerInput {
+    PlayerInput {
+        jump: tick % 50 < 10,
+        left: tick % 240 >= 180,
+        right: tick % 240 < 90,
+        down: false,
+        dash: tick % 120 == 0,
+    }
+}
+
+#[test]
+fn recorded_run_plays_back_the_same() {
+    let path = std::env::temp_dir().join(format!("jump-or-die-replay-{}.ron", std::process::id()));
+
+    let mut recorded = HeadlessGame::new(
+        Duration::from_secs_f64(game::SIMULATION_STEP),
+        Some(7),
+        Tuning::default(),
+    );
+    recorded.record(path.clone());
+    let mut ticks = 0;
+    while ticks < SCRIPTED_TICKS && *recorded.state() != GameState::GameOver {
+        recorded.set_input(scripted_input(ticks));
+        recorded.tick();
+        ticks += 1;
+    }
+    recorded.set_input(PlayerInput::default());
+    ticks += recorded.run_until_game_over(MAX_TICKS);
+    assert_eq!(*recorded.state(), GameState::GameOver);
+
+    let replay = Replay::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+    let mut replayed = HeadlessGame::from_replay(replay);
+    assert_eq!(replayed.run_until_game_over(MAX_TICKS), ticks);
+
+    assert_eq!(replayed.seed(), recorded.seed());
+    assert_eq!(replayed.game().score, recorded.game().score);
+    assert_eq!(replayed.game().time_survived, recorded.game().time_survived);
+    assert_eq!(
+        replayed.player_transform().translation,
+        recorded.player_transform().translation
+    );
+}